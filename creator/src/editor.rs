@@ -215,6 +215,9 @@ impl TheTrait for Editor {
             }
             if self.server.state == ServerState::Running {
                 self.server.tick();
+                if let Some(error) = self.server.take_errors().pop() {
+                    ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), error));
+                }
                 self.panels
                     .update_code_object(ui, ctx, &mut self.server, &mut self.server_ctx);
             }
@@ -242,6 +245,7 @@ impl TheTrait for Editor {
                     &mut self.project,
                     &mut self.server,
                     &mut self.server_ctx,
+                    &mut self.tileeditor,
                 );
                 if self.panels.handle_event(
                     &event,
@@ -328,6 +332,14 @@ impl TheTrait for Editor {
                                 self.project.get_region_mut(&self.server_ctx.curr_region)
                             {
                                 region.characters.insert(character.id, character.clone());
+
+                                let mut region_undo = RegionUndo::new(region.id);
+                                region_undo.record_character(
+                                    character.id,
+                                    None,
+                                    Some(character.clone()),
+                                );
+                                self.tileeditor.add_undo(region_undo, ctx);
                             }
 
                             // Add the character instance to the server
@@ -352,6 +364,114 @@ impl TheTrait for Editor {
 
                             //     self.sidebar.code_editor.set_debug_module(debug_module, ui);
                             // }
+                        } else if drop.id.name.starts_with("Item") {
+                            let mut instance = TheCodeBundle::new();
+
+                            let mut init = TheCodeGrid {
+                                name: "init".into(),
+                                ..Default::default()
+                            };
+                            init.insert_atom(
+                                (0, 0),
+                                TheCodeAtom::ObjectSet("self".to_string(), "position".to_string()),
+                            );
+                            init.insert_atom((1, 0), TheCodeAtom::Assignment("=".to_string()));
+                            init.insert_atom(
+                                (2, 0),
+                                TheCodeAtom::Value(TheValue::Position(vec3f(
+                                    location.x as f32,
+                                    location.y as f32,
+                                    0.0,
+                                ))),
+                            );
+                            instance.insert_grid(init);
+
+                            let item = Item {
+                                id: instance.id,
+                                item_id: drop.id.uuid,
+                                instance,
+                            };
+
+                            // Add the item instance to the region content list
+
+                            let mut name = "Item".to_string();
+                            if let Some(item) = self.project.items.get(&drop.id.uuid) {
+                                name = item.name.clone();
+                            }
+
+                            if let Some(list) = ui.get_list_layout("Region Content List") {
+                                let mut list_item = TheListItem::new(TheId::named_with_id(
+                                    "Region Content List Item",
+                                    item.id,
+                                ));
+                                list_item.set_text(name);
+                                list_item.set_state(TheWidgetState::Selected);
+                                list_item.add_value_column(100, TheValue::Text("Item".to_string()));
+
+                                list.deselect_all();
+                                list.add_item(list_item, ctx);
+                                list.select_item(item.id, ctx);
+                            }
+
+                            // Add the item instance to the project and the server
+
+                            if let Some(region) =
+                                self.project.get_region_mut(&self.server_ctx.curr_region)
+                            {
+                                region.items.insert(item.id, item.clone());
+
+                                let mut region_undo = RegionUndo::new(region.id);
+                                region_undo.record_item(item.id, None, Some(item.clone()));
+                                self.tileeditor.add_undo(region_undo, ctx);
+                            }
+
+                            self.server
+                                .add_item_instance_to_region(self.server_ctx.curr_region, item);
+                        }
+                    }
+                    TheEvent::KeyCodeDown(TheValue::KeyCode(key)) => {
+                        if self.server.state == ServerState::Running
+                            && self.game_has_keyboard(ui, ctx)
+                        {
+                            if let Some(player) = self.server_ctx.curr_player {
+                                let direction = match key {
+                                    TheKeyCode::Up => Some(PlayerDirection::North),
+                                    TheKeyCode::Right => Some(PlayerDirection::East),
+                                    TheKeyCode::Down => Some(PlayerDirection::South),
+                                    TheKeyCode::Left => Some(PlayerDirection::West),
+                                    _ => None,
+                                };
+                                if let Some(direction) = direction {
+                                    self.server.player_action(
+                                        player,
+                                        PlayerAction::new("move", direction),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    TheEvent::KeyDown(TheValue::Char(c)) => {
+                        if self.server.state == ServerState::Running
+                            && self.game_has_keyboard(ui, ctx)
+                        {
+                            if let Some(player) = self.server_ctx.curr_player {
+                                if c.to_ascii_lowercase() == 'f' {
+                                    self.server_ctx.fov_mode = !self.server_ctx.fov_mode;
+                                    redraw = true;
+                                }
+                                let action = match c.to_ascii_lowercase() {
+                                    'u' => Some("use"),
+                                    't' => Some("take"),
+                                    'a' => Some("attack"),
+                                    _ => None,
+                                };
+                                if let Some(action) = action {
+                                    self.server.player_action(
+                                        player,
+                                        PlayerAction::new(action, PlayerDirection::None),
+                                    );
+                                }
+                            }
                         }
                     }
                     TheEvent::FileRequesterResult(id, paths) => {
@@ -370,7 +490,14 @@ impl TheTrait for Editor {
                                 ctx.ui.send(TheEvent::SetStatusText(
                                     TheId::empty(),
                                     "Project loaded successfully.".to_string(),
-                                ))
+                                ));
+                                let diagnostics = self.server.take_diagnostics();
+                                self.sidebar.show_diagnostics(
+                                    ui,
+                                    ctx,
+                                    &mut self.server_ctx,
+                                    diagnostics,
+                                );
                             }
                         } else if id.name == "Save As" {
                             for p in paths {
@@ -450,13 +577,62 @@ impl TheTrait for Editor {
                             ctx.ui.clear_hover();
                             redraw = true;
                         }
+                        // Region Crop / Shift
+                        else if id.name == "Region Crop" || id.name == "Region Shift" {
+                            let mut offset = vec2i(0, 0);
+                            if let Some(edit) = ui.get_text_line_edit("Region Shift X Edit") {
+                                offset.x = edit.text().trim().parse().unwrap_or(0);
+                            }
+                            if let Some(edit) = ui.get_text_line_edit("Region Shift Y Edit") {
+                                offset.y = edit.text().trim().parse().unwrap_or(0);
+                            }
+
+                            if let Some(region) =
+                                self.project.get_region_mut(&self.server_ctx.curr_region)
+                            {
+                                let region_undo = if id.name == "Region Crop" {
+                                    region.crop_to_content()
+                                } else {
+                                    region.shift_content(offset)
+                                };
+                                if !region_undo.is_empty() {
+                                    self.apply_region_undo(ui, ctx, &region_undo);
+                                    self.tileeditor.add_undo(region_undo, ctx);
+                                }
+                            }
+                            redraw = true;
+                        }
                         // Server
                         else if id.name == "Play" {
+                            // The selected character instance is controlled by the user.
+                            if self.server.state == ServerState::Stopped {
+                                self.server_ctx.curr_player =
+                                    self.server_ctx.curr_character_instance;
+                                if let Some(player) = self.server_ctx.curr_player {
+                                    self.server.set_player(player, true);
+                                }
+                            }
                             self.server.start();
-                            ctx.ui.send(TheEvent::SetStatusText(
-                                TheId::empty(),
-                                "Server has been started.".to_string(),
-                            ));
+                            let text = if self.server_ctx.curr_player.is_some() {
+                                format!(
+                                    "Server has been started (Seed {}). Move the selected character with the cursor keys, U to use, T to take, A to attack, F to toggle the field of view.",
+                                    self.server.seed()
+                                )
+                            } else {
+                                format!("Server has been started (Seed {}).", self.server.seed())
+                            };
+                            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), text));
+
+                            // Broken grids do not execute, report them when starting.
+                            let diagnostics = self.server.take_diagnostics();
+                            if !diagnostics.is_empty() {
+                                self.sidebar.show_diagnostics(
+                                    ui,
+                                    ctx,
+                                    &mut self.server_ctx,
+                                    diagnostics,
+                                );
+                            }
                             update_server_icons = true;
                         } else if id.name == "Pause" {
                             if self.server.state == ServerState::Running {
@@ -472,8 +648,17 @@ impl TheTrait for Editor {
                         } else if id.name == "Stop" {
                             self.server.set_project(self.project.clone());
                             self.server.stop();
+                            self.server_ctx.curr_player = None;
+                            let diagnostics = self.server.take_diagnostics();
+                            self.sidebar
+                                .show_diagnostics(ui, ctx, &mut self.server_ctx, diagnostics);
                             update_server_icons = true;
                         } else {
+                            // A pending brush stroke becomes the last undo step first.
+                            if id.name == "Undo" || id.name == "Redo" {
+                                self.tileeditor.finish_stroke(ctx);
+                            }
+
                             let mut data: Option<(TheId, String)> = None;
                             if id.name == "Undo" && ctx.ui.undo_stack.has_undo() {
                                 data = Some(ctx.ui.undo_stack.undo());
@@ -484,6 +669,7 @@ impl TheTrait for Editor {
                             if let Some((id, json)) = data {
                                 #[allow(clippy::single_match)]
                                 match id.name.as_str() {
+                                    // Full region snapshots, only left by older edits.
                                     "RegionChanged" => {
                                         let region = Region::from_json(json.as_str());
                                         for (index, r) in self.project.regions.iter().enumerate() {
@@ -502,6 +688,11 @@ impl TheTrait for Editor {
                                             }
                                         }
                                     }
+                                    "RegionEdited" => {
+                                        if let Some(region_undo) = RegionUndo::from_json(&json) {
+                                            self.apply_region_undo(ui, ctx, &region_undo);
+                                        }
+                                    }
                                     _ => {}
                                 }
                                 redraw = true;
@@ -521,7 +712,32 @@ impl TheTrait for Editor {
                     }
                     TheEvent::ValueChanged(id, value) => {
                         //println!("{:?} {:?}", id, value);
-                        if id.name == "Region Name Edit" {
+                        if id.name == "Region Width Edit" || id.name == "Region Height Edit" {
+                            let anchor = if let Some(drop_down) =
+                                ui.get_drop_down_menu("Region Anchor Dropdown")
+                            {
+                                RegionAnchor::from_index(drop_down.selected_index() as u8)
+                                    .unwrap_or(RegionAnchor::TopLeft)
+                            } else {
+                                RegionAnchor::TopLeft
+                            };
+
+                            if let Some(v) = value.to_i32() {
+                                if let Some(region) =
+                                    self.project.get_region_mut(&self.server_ctx.curr_region)
+                                {
+                                    let region_undo = if id.name == "Region Width Edit" {
+                                        region.resize(v, region.height, anchor)
+                                    } else {
+                                        region.resize(region.width, v, anchor)
+                                    };
+                                    if !region_undo.is_empty() {
+                                        self.apply_region_undo(ui, ctx, &region_undo);
+                                        self.tileeditor.add_undo(region_undo, ctx);
+                                    }
+                                }
+                            }
+                        } else if id.name == "Region Name Edit" {
                             if let Some(list_id) =
                                 self.sidebar.get_selected_in_list_layout(ui, "Region List")
                             {
@@ -535,6 +751,24 @@ impl TheTrait for Editor {
                                     }
                                 }
                             }
+                        } else if id.name == "Area Name Edit" {
+                            if let Some(list_id) = self
+                                .sidebar
+                                .get_selected_in_list_layout(ui, "Region Content List")
+                            {
+                                ctx.ui.send(TheEvent::SetValue(list_id.uuid, value));
+                            }
+                        } else if id.name == "Region Content List Item" {
+                            if let Some(region) =
+                                self.project.get_region_mut(&self.server_ctx.curr_region)
+                            {
+                                if let Some(area) = region.areas.get_mut(&id.uuid) {
+                                    if let Some(text) = value.to_string() {
+                                        area.name = text;
+                                    }
+                                    self.server.update_region(region);
+                                }
+                            }
                         } else if id.name == "Character Name Edit" {
                             if let Some(list_id) = self
                                 .sidebar
@@ -585,6 +819,8 @@ impl TheTrait for Editor {
                                 if let Some(text) = value.to_string() {
                                     code.name = text;
                                 }
+                                // Functions are called by the name of their bundle.
+                                self.server.insert_code(code.clone());
                             }
                         }
                     }
@@ -602,9 +838,101 @@ impl TheTrait for Editor {
 
 pub trait EldironEditor {
     fn update_server_state_icons(&mut self, ui: &mut TheUI);
+    fn game_has_keyboard(&self, ui: &mut TheUI, ctx: &TheContext) -> bool;
+    fn apply_region_undo(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        region_undo: &RegionUndo,
+    );
 }
 
 impl EldironEditor for Editor {
+    /// Returns true if the key presses belong to the running game, i.e. the region view has
+    /// the focus or neither a text edit nor the code editor is focused.
+    fn game_has_keyboard(&self, ui: &mut TheUI, ctx: &TheContext) -> bool {
+        let focus = if let Some(focus) = &ctx.ui.focus {
+            focus.uuid
+        } else {
+            return true;
+        };
+
+        if let Some(rgba_layout) = ui.get_rgba_layout("Region Editor") {
+            if rgba_layout.rgba_view_mut().id().uuid == focus {
+                return true;
+            }
+        }
+
+        if let Some(code_layout) = ui.get_code_layout("Code Editor") {
+            if code_layout.code_view_mut().id().uuid == focus {
+                return false;
+            }
+        }
+
+        if let Some(widget) = ui.get_widget_id(focus) {
+            return widget.as_text_line_edit().is_none();
+        }
+
+        true
+    }
+
+    /// Applies a region edit from the undo stack to the project and the server.
+    fn apply_region_undo(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        region_undo: &RegionUndo,
+    ) {
+        if let Some(region) = self.project.get_region_mut(&region_undo.region_id) {
+            region_undo.apply(region);
+            self.server.update_region(region);
+
+            for (id, before, after) in &region_undo.characters {
+                if let Some(character) = after {
+                    if before.is_some() {
+                        // The instance changed, replace it.
+                        self.server.remove_character_instance(region.id, *id);
+                    }
+                    self.server.add_character_instance_to_region(region.id, character.clone());
+                } else {
+                    self.server.remove_character_instance(region.id, *id);
+                    if self.server_ctx.curr_character_instance == Some(*id) {
+                        self.server_ctx.curr_character_instance = None;
+                        self.server_ctx.curr_character = None;
+                    }
+                }
+            }
+
+            for (id, before, after) in &region_undo.items {
+                if let Some(item) = after {
+                    if before.is_some() {
+                        self.server.remove_item_instance(region.id, *id);
+                    }
+                    self.server.add_item_instance_to_region(region.id, item.clone());
+                } else {
+                    self.server.remove_item_instance(region.id, *id);
+                }
+            }
+
+            if region.id == self.server_ctx.curr_region {
+                if region_undo.size.is_some() {
+                    // The region was resized, resize the editor buffer.
+                    if let Some(rgba_layout) = ui.get_rgba_layout("Region Editor") {
+                        if let Some(rgba_view) = rgba_layout.rgba_view_mut().as_rgba_view() {
+                            let width = region.width * region.grid_size;
+                            let height = region.height * region.grid_size;
+                            rgba_view
+                                .set_buffer(TheRGBABuffer::new(TheDim::new(0, 0, width, height)));
+                        }
+                    }
+                    ctx.ui.relayout = true;
+                }
+                self.sidebar.apply_region(ui, ctx, Some(&*region), &mut self.server);
+                self.tileeditor.redraw_region(ui, &mut self.server, ctx, &self.server_ctx);
+            }
+        }
+    }
+
     fn update_server_state_icons(&mut self, ui: &mut TheUI) {
         if self.server.state == ServerState::Running {
             if let Some(button) = ui.get_widget("Play") {
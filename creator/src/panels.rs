@@ -16,6 +16,208 @@ impl Panels {
             None,
         ));
 
+        codeeditor.add_external(TheExternalCode::new(
+            "GoTo".to_string(),
+            "Walks one step towards the target position or the character with the given name. Returns true once the target is reached, false while walking or if the target is blocked or unreachable."
+                .to_string(),
+            vec!["Target".to_string()],
+            vec![TheValue::Position(vec3f(0.0, 0.0, 0.0))],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Teleport".to_string(),
+            "Moves the character to the given position. If a region name is given, the character is transferred into that region after the tick, unless the position there is blocked or occupied. Returns false if the position in the current region is blocked or occupied."
+                .to_string(),
+            vec!["Region".to_string(), "X".to_string(), "Y".to_string()],
+            vec![TheValue::Text(String::new()), TheValue::Int(0), TheValue::Int(0)],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Take".to_string(),
+            "Takes the item with the given name (or any item if empty) from the tile of the character into its inventory."
+                .to_string(),
+            vec!["Item".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Drop".to_string(),
+            "Drops the item with the given name (or any item if empty) from the inventory onto the tile of the character."
+                .to_string(),
+            vec!["Item".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Use".to_string(),
+            "Uses the item with the given name from the inventory, executing its on_use function."
+                .to_string(),
+            vec!["Item".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Equip".to_string(),
+            "Equips the item with the given name from the inventory in the slot given by its slot property."
+                .to_string(),
+            vec!["Item".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Call".to_string(),
+            "Calls the function of the given name from the Code section (\"Bundle\" or \"Bundle.grid\"). The arguments are available as args.arg1 to args.arg3, the function returns the value of args.result."
+                .to_string(),
+            vec![
+                "Function".to_string(),
+                "Arg 1".to_string(),
+                "Arg 2".to_string(),
+                "Arg 3".to_string(),
+            ],
+            vec![
+                TheValue::Text(String::new()),
+                TheValue::Int(0),
+                TheValue::Int(0),
+                TheValue::Int(0),
+            ],
+            Some(TheValue::Empty),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "SendEvent".to_string(),
+            "Sends the event with the given name and value to the characters with the given name. Their on_<event> function is executed in the next tick, with the value in event_value and the name of the sender in event_sender."
+                .to_string(),
+            vec!["Target".to_string(), "Event".to_string(), "Value".to_string()],
+            vec![
+                TheValue::Text(String::new()),
+                TheValue::Text(String::new()),
+                TheValue::Int(0),
+            ],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "StartTimer".to_string(),
+            "Executes the function with the given name on the character after the given number of ticks. If Repeat is true the function is executed every given number of ticks. Returns true if the timer was started."
+                .to_string(),
+            vec!["Function".to_string(), "Ticks".to_string(), "Repeat".to_string()],
+            vec![
+                TheValue::Text(String::new()),
+                TheValue::Int(4),
+                TheValue::Bool(false),
+            ],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "StopTimer".to_string(),
+            "Stops the timer of the character for the function with the given name. Returns true if a timer was running."
+                .to_string(),
+            vec!["Function".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "ScheduleAt".to_string(),
+            "Executes the function with the given name on the character every day at the given in-game time. Returns true if the function was scheduled."
+                .to_string(),
+            vec!["Function".to_string(), "Hour".to_string(), "Minute".to_string()],
+            vec![TheValue::Text(String::new()), TheValue::Int(8), TheValue::Int(0)],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Hour".to_string(),
+            "Returns the hour of the in-game time.".to_string(),
+            vec![],
+            vec![],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Minute".to_string(),
+            "Returns the minute of the in-game time.".to_string(),
+            vec![],
+            vec![],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Day".to_string(),
+            "Returns the in-game day, starting at 1.".to_string(),
+            vec![],
+            vec![],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "DayPhase".to_string(),
+            "Returns the phase of the in-game day: dawn, day, dusk or night.".to_string(),
+            vec![],
+            vec![],
+            Some(TheValue::Text(String::new())),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Roll".to_string(),
+            "Rolls the given dice expression, e.g. 2d6+3, and returns the result.".to_string(),
+            vec!["Dice".to_string()],
+            vec![TheValue::Text("1d6".to_string())],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Attack".to_string(),
+            "Attacks the character with the given name (or any character if empty) next to the character. The damage is rolled from the attack property (e.g. 1d6) minus the defense property of the target. Returns the damage, 0 on a miss."
+                .to_string(),
+            vec!["Target".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "TakeDamage".to_string(),
+            "Rolls the given dice expression and subtracts it from the hit points (hp) of the character. Characters die at 0 hit points. Returns the damage."
+                .to_string(),
+            vec!["Amount".to_string()],
+            vec![TheValue::Text("1d4".to_string())],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "Heal".to_string(),
+            "Rolls the given dice expression and adds it to the hit points (hp) of the character, up to max_hp. Returns the healed amount."
+                .to_string(),
+            vec!["Amount".to_string()],
+            vec![TheValue::Text("1d4".to_string())],
+            Some(TheValue::Int(0)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "CanSee".to_string(),
+            "Returns true if the character sees the character with the given name or role (or any character if empty), i.e. it is within the vision radius (vision property, default 8) and not hidden behind walls."
+                .to_string(),
+            vec!["Target".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Bool(false)),
+        ));
+
+        codeeditor.add_external(TheExternalCode::new(
+            "NearestVisible".to_string(),
+            "Returns the name of the nearest character visible to the character with the given role or name (or any character if empty). Returns an empty text if no character is visible."
+                .to_string(),
+            vec!["Role".to_string()],
+            vec![TheValue::Text(String::new())],
+            Some(TheValue::Text(String::new())),
+        ));
+
         codeeditor.add_external(TheExternalCode::new(
             "Pulse".to_string(),
             "Counts up to a certain value and returns true on completion. Then restarts."
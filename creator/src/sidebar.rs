@@ -1,7 +1,7 @@
 use crate::editor::{CODEEDITOR, SIDEBARMODE, TILEMAPEDITOR};
 use crate::prelude::*;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum SidebarMode {
     Region,
     Character,
@@ -165,6 +165,33 @@ impl Sidebar {
         toolbar_canvas.set_layout(toolbar_hlayout);
         content_canvas.set_top(toolbar_canvas);
 
+        let mut area_add_button = TheTraybarButton::new(TheId::named("Area Add"));
+        area_add_button.set_icon_name("icon_role_add".to_string());
+        area_add_button.set_status_text("Add a new area from the selection in the region editor.");
+
+        let mut area_remove_button = TheTraybarButton::new(TheId::named("Area Remove"));
+        area_remove_button.set_icon_name("icon_role_remove".to_string());
+        area_remove_button.set_status_text("Remove the selected area.");
+        area_remove_button.set_disabled(true);
+
+        let mut name_edit = TheTextLineEdit::new(TheId::named("Area Name Edit"));
+        name_edit.limiter_mut().set_max_width(200);
+        name_edit.set_status_text("Edit the name of the area.");
+        name_edit.set_disabled(true);
+
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(vec4i(5, 2, 5, 2));
+        toolbar_hlayout.add_widget(Box::new(area_add_button));
+        toolbar_hlayout.add_widget(Box::new(area_remove_button));
+        toolbar_hlayout.add_widget(Box::new(TheHDivider::new(TheId::empty())));
+        toolbar_hlayout.add_widget(Box::new(name_edit));
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        content_canvas.set_bottom(toolbar_canvas);
+
         region_tab.add_canvas("Content".to_string(), content_canvas);
 
         // Region Settings
@@ -174,14 +201,15 @@ impl Sidebar {
         let mut text_layout: TheTextLayout = TheTextLayout::new(TheId::empty());
         text_layout
             .limiter_mut()
-            .set_max_size(vec2i(self.width, 250));
-        let mut drop_down = TheDropdownMenu::new(TheId::named("Region Settings Dropdown"));
+            .set_max_size(vec2i(self.width, 350));
+        let mut drop_down = TheDropdownMenu::new(TheId::named("Region Anchor Dropdown"));
         drop_down.add_option("Top / Left".to_string());
         drop_down.add_option("Top / Right".to_string());
         drop_down.add_option("Bottom / Left".to_string());
         drop_down.add_option("Bottom / Right".to_string());
+        drop_down.add_option("Center".to_string());
         drop_down.set_status_text(
-            "On region size changes the region will grow or shrink from the given corner.",
+            "On region size changes the content stays at the given corner or the center.",
         );
         text_layout.add_pair("Grow / Shrink From".to_string(), Box::new(drop_down));
         let mut width_edit = TheTextLineEdit::new(TheId::named("Region Width Edit"));
@@ -196,6 +224,26 @@ impl Sidebar {
         grid_edit.set_range(TheValue::RangeI32(1..=1000));
         grid_edit.set_status_text("The size of the region grid in pixels.");
         text_layout.add_pair("Grid Size".to_string(), Box::new(grid_edit));
+        let mut crop_button = TheTraybarButton::new(TheId::named("Region Crop"));
+        crop_button.set_text("Crop to Content".to_string());
+        crop_button.set_status_text("Shrink the region to the bounding rectangle of its content.");
+        text_layout.add_pair("".to_string(), Box::new(crop_button));
+        let mut shift_x_edit = TheTextLineEdit::new(TheId::named("Region Shift X Edit"));
+        shift_x_edit.set_text("0".to_string());
+        shift_x_edit.set_range(TheValue::RangeI32(-100000..=100000));
+        shift_x_edit.set_status_text("The horizontal offset to shift the content by.");
+        text_layout.add_pair("Shift X".to_string(), Box::new(shift_x_edit));
+        let mut shift_y_edit = TheTextLineEdit::new(TheId::named("Region Shift Y Edit"));
+        shift_y_edit.set_text("0".to_string());
+        shift_y_edit.set_range(TheValue::RangeI32(-100000..=100000));
+        shift_y_edit.set_status_text("The vertical offset to shift the content by.");
+        text_layout.add_pair("Shift Y".to_string(), Box::new(shift_y_edit));
+        let mut shift_button = TheTraybarButton::new(TheId::named("Region Shift"));
+        shift_button.set_text("Shift Content".to_string());
+        shift_button.set_status_text(
+            "Move all content by the offset. Content moved outside of the region is removed.",
+        );
+        text_layout.add_pair("".to_string(), Box::new(shift_button));
 
         settings_canvas.set_layout(text_layout);
         region_tab.add_canvas("Settings".to_string(), settings_canvas);
@@ -410,6 +458,7 @@ impl Sidebar {
         self.apply_code(ui, ctx, None);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn handle_event(
         &mut self,
         event: &TheEvent,
@@ -418,6 +467,7 @@ impl Sidebar {
         project: &mut Project,
         server: &mut Server,
         server_ctx: &mut ServerContext,
+        tileeditor: &mut TileEditor,
     ) -> bool {
         let mut redraw = false;
 
@@ -434,6 +484,13 @@ impl Sidebar {
                     drop.set_offset(*offset);
                     ui.style.create_drop_image(&mut drop, ctx);
                     ctx.ui.set_drop(drop);
+                } else if id.name == "Item Item" {
+                    let mut drop = TheDrop::new(id.clone());
+                    drop.set_title(format!("Item: {}", text));
+                    drop.set_text(text.clone());
+                    drop.set_offset(*offset);
+                    ui.style.create_drop_image(&mut drop, ctx);
+                    ctx.ui.set_drop(drop);
                 }
             }
             TheEvent::ValueChanged(id, value) => {
@@ -532,6 +589,70 @@ impl Sidebar {
                     }
                 } else if id.name == "Region Settings" {
                     self.show_region_settings(ui, ctx);
+                } else if id.name == "Area Add" {
+                    let mut selection = FxHashSet::default();
+                    if let Some(rgba_layout) = ui.get_rgba_layout("Region Editor") {
+                        if let Some(rgba_view) = rgba_layout.rgba_view_mut().as_rgba_view() {
+                            selection = rgba_view.selection();
+                        }
+                    }
+
+                    if selection.is_empty() {
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            "Select the tiles of the area in the region editor first.".to_string(),
+                        ));
+                    } else if let Some(region) = project.get_region_mut(&server_ctx.curr_region)
+                    {
+                        let mut area = Area::new();
+                        area.area = selection;
+                        let area_id = area.id;
+                        region.areas.insert(area.id, area.clone());
+
+                        let mut region_undo = RegionUndo::new(region.id);
+                        region_undo.record_area(area_id, None, Some(area));
+                        tileeditor.add_undo(region_undo, ctx);
+                        server.update_region(region);
+
+                        self.apply_region(
+                            ui,
+                            ctx,
+                            project.get_region(&server_ctx.curr_region),
+                            server,
+                        );
+                        if let Some(list) = ui.get_list_layout("Region Content List") {
+                            list.select_item(area_id, ctx);
+                        }
+                        ctx.ui.send_widget_state_changed(
+                            &TheId::named_with_id("Region Content List Item", area_id),
+                            TheWidgetState::Selected,
+                        );
+                        redraw = true;
+                    }
+                } else if id.name == "Area Remove" {
+                    if let Some(selected) =
+                        self.get_selected_in_list_layout(ui, "Region Content List")
+                    {
+                        if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
+                            if let Some(area) = region.areas.remove(&selected.uuid) {
+                                let mut region_undo = RegionUndo::new(region.id);
+                                region_undo.record_area(area.id, Some(area), None);
+                                tileeditor.add_undo(region_undo, ctx);
+                                server.update_region(region);
+
+                                if let Some(list) = ui.get_list_layout("Region Content List") {
+                                    list.remove(selected);
+                                }
+                                self.apply_area(ui, ctx, None);
+                                redraw = true;
+                            }
+                        }
+                    }
+                } else if id.name == "Region Content List Item" {
+                    let area = project
+                        .get_region(&server_ctx.curr_region)
+                        .and_then(|region| region.areas.get(&id.uuid));
+                    self.apply_area(ui, ctx, area);
                 } else if id.name == "Character Add" {
                     if let Some(list_layout) = ui.get_list_layout("Character List") {
                         let mut bundle = TheCodeBundle::new();
@@ -595,11 +716,44 @@ impl Sidebar {
                         server_ctx.curr_character = Some(id.uuid);
                         //server_ctx.curr_character_instance = None;
                         self.apply_character(ui, ctx, Some(c));
+                        self.highlight_diagnostics(ui, server_ctx);
                         redraw = true;
                     }
                 } else if id.name == "Item Add" {
                     if let Some(list_layout) = ui.get_list_layout("Item List") {
-                        let bundle = TheCodeBundle::new();
+                        let mut bundle = TheCodeBundle::new();
+
+                        let mut init = TheCodeGrid {
+                            name: "init".into(),
+                            ..Default::default()
+                        };
+                        init.insert_atom(
+                            (0, 0),
+                            TheCodeAtom::ObjectSet("self".to_string(), "name".to_string()),
+                        );
+                        init.insert_atom((1, 0), TheCodeAtom::Assignment("=".to_string()));
+                        init.insert_atom(
+                            (2, 0),
+                            TheCodeAtom::Value(TheValue::Text("Unnamed".to_string())),
+                        );
+
+                        init.insert_atom(
+                            (0, 2),
+                            TheCodeAtom::ObjectSet("self".to_string(), "tile".to_string()),
+                        );
+                        init.insert_atom((1, 2), TheCodeAtom::Assignment("=".to_string()));
+                        init.insert_atom(
+                            (2, 2),
+                            TheCodeAtom::Value(TheValue::Tile("Name".to_string(), Uuid::nil())),
+                        );
+
+                        bundle.insert_grid(init);
+
+                        let on_use = TheCodeGrid {
+                            name: "on_use".into(),
+                            ..Default::default()
+                        };
+                        bundle.insert_grid(on_use);
 
                         let mut item =
                             TheListItem::new(TheId::named_with_id("Item Item", bundle.id));
@@ -612,6 +766,7 @@ impl Sidebar {
                             .send_widget_state_changed(&id, TheWidgetState::Selected);
 
                         self.apply_item(ui, ctx, Some(&bundle));
+                        server.insert_item(bundle.clone());
                         project.add_item(bundle);
                     }
                 } else if id.name == "Item Remove" {
@@ -625,11 +780,19 @@ impl Sidebar {
                 } else if id.name == "Item Item" {
                     if let Some(c) = project.items.get(&id.uuid) {
                         self.apply_item(ui, ctx, Some(c));
+                        self.highlight_diagnostics(ui, server_ctx);
                         redraw = true;
                     }
                 } else if id.name == "Code Add" {
                     if let Some(list_layout) = ui.get_list_layout("Code List") {
-                        let bundle = TheCodeBundle::new();
+                        let mut bundle = TheCodeBundle::new();
+
+                        // The main grid is executed when the bundle is called by its name.
+                        let main = TheCodeGrid {
+                            name: "main".into(),
+                            ..Default::default()
+                        };
+                        bundle.insert_grid(main);
 
                         let mut item =
                             TheListItem::new(TheId::named_with_id("Code Item", bundle.id));
@@ -642,12 +805,14 @@ impl Sidebar {
                             .send_widget_state_changed(&id, TheWidgetState::Selected);
 
                         self.apply_code(ui, ctx, Some(&bundle));
+                        server.insert_code(bundle.clone());
                         project.add_code(bundle);
                     }
                 } else if id.name == "Code Remove" {
-                    if let Some(list_layout) = ui.get_list_layout("Item List") {
+                    if let Some(list_layout) = ui.get_list_layout("Code List") {
                         if let Some(selected) = list_layout.selected() {
                             list_layout.remove(selected.clone());
+                            server.remove_code(&selected.uuid);
                             project.remove_code(&selected.uuid);
                             self.apply_code(ui, ctx, None);
                         }
@@ -655,6 +820,7 @@ impl Sidebar {
                 } else if id.name == "Code Item" {
                     if let Some(c) = project.codes.get(&id.uuid) {
                         self.apply_code(ui, ctx, Some(c));
+                        self.highlight_diagnostics(ui, server_ctx);
                         redraw = true;
                     }
                 }
@@ -706,6 +872,10 @@ impl Sidebar {
                                 .set_selection(FxHashSet::default());
                         }
                     }
+                } else if id.name == "Tilemap Editor Autotile Set"
+                    || id.name == "Tilemap Editor Autotile Remove"
+                {
+                    self.edit_autotile(id.name == "Tilemap Editor Autotile Set", ui, ctx, project);
                 } else if id.name == "Tilemap Editor Add Selection" {
                     let mut clear_selection = false;
 
@@ -735,6 +905,33 @@ impl Sidebar {
                                 tile.blocking = block_widget.state() == TheWidgetState::Selected;
                             }
 
+                            if let Some(widget) = ui
+                                .canvas
+                                .get_widget(Some(&"Tilemap Editor Vision".to_string()), None)
+                            {
+                                tile.flags.blocks_vision =
+                                    widget.state() == TheWidgetState::Selected;
+                            }
+
+                            if let Some(widget) = ui
+                                .canvas
+                                .get_widget(Some(&"Tilemap Editor Water".to_string()), None)
+                            {
+                                tile.flags.water = widget.state() == TheWidgetState::Selected;
+                            }
+
+                            if let Some(edit) = ui.get_text_line_edit("Tilemap Editor Damage") {
+                                tile.flags.damage = edit.text().parse().unwrap_or(0).max(0);
+                            }
+
+                            if let Some(edit) = ui.get_text_line_edit("Tilemap Editor Cost") {
+                                tile.flags.movement_cost = edit.text().parse().unwrap_or(1).max(1);
+                            }
+
+                            if let Some(edit) = ui.get_text_line_edit("Tilemap Editor Light") {
+                                tile.flags.light = edit.text().parse().unwrap_or(0).max(0);
+                            }
+
                             if let Some(role_widget) = ui.get_drop_down_menu("Tilemap Editor Role")
                             {
                                 let index = role_widget.selected_index();
@@ -754,14 +951,7 @@ impl Sidebar {
                                             tile.id,
                                         ));
                                         item.set_text(tile.name.clone());
-                                        let mut sub_text = if tile.blocking {
-                                            "Blocking".to_string()
-                                        } else {
-                                            "Non-Blocking".to_string()
-                                        };
-                                        sub_text +=
-                                            ("  ".to_string() + tile.role.to_string()).as_str();
-                                        item.set_sub_text(sub_text);
+                                        item.set_sub_text(self.tile_sub_text(&tile));
                                         item.set_state(TheWidgetState::Selected);
                                         item.set_size(42);
                                         item.set_associated_layout(list_layout_id);
@@ -952,32 +1142,62 @@ impl Sidebar {
                         if let Some(code_view) = layout.code_view_mut().as_code_view() {
                             let grid = code_view.codegrid_mut();
 
+                            let grid_id = grid.id;
                             let rc = server.compiler().compile(grid);
 
-                            if let Ok(_module) = rc {
-                                let bundle: TheCodeBundle = CODEEDITOR.lock().unwrap().get_bundle();
-                                CODEEDITOR.lock().unwrap().set_compiled(true, ui, ctx);
-
-                                // Successfully compiled, transfer the bundle to the server.
-
-                                if *SIDEBARMODE.lock().unwrap() == SidebarMode::Region {
-                                    if let Some(character_instance) =
-                                        server_ctx.curr_character_instance
-                                    {
-                                        server.update_character_instance_bundle(
-                                            server_ctx.curr_region,
-                                            character_instance,
-                                            CODEEDITOR.lock().unwrap().get_bundle(),
-                                        );
+                            // Forget the earlier errors of this grid.
+                            server_ctx.diagnostics.retain(|d| d.grid_id != grid_id);
+
+                            match rc {
+                                Ok(_module) => {
+                                    let bundle: TheCodeBundle =
+                                        CODEEDITOR.lock().unwrap().get_bundle();
+                                    CODEEDITOR.lock().unwrap().set_compiled(true, ui, ctx);
+
+                                    // Successfully compiled, transfer the bundle to the server.
+
+                                    let mode = *SIDEBARMODE.lock().unwrap();
+                                    if mode == SidebarMode::Region {
+                                        if let Some(character_instance) =
+                                            server_ctx.curr_character_instance
+                                        {
+                                            server.update_character_instance_bundle(
+                                                server_ctx.curr_region,
+                                                character_instance,
+                                                bundle,
+                                            );
+                                        }
+                                    } else if mode == SidebarMode::Character {
+                                        server.insert_character(bundle);
+                                    } else if mode == SidebarMode::Item {
+                                        server.insert_item(bundle);
+                                    } else if mode == SidebarMode::Code {
+                                        server.insert_code(bundle);
                                     }
-                                } else if *SIDEBARMODE.lock().unwrap() == SidebarMode::Character {
-                                    server.insert_character(bundle);
+
+                                    ctx.ui.send(TheEvent::SetStatusText(
+                                        TheId::empty(),
+                                        "Compiled successfully.".to_string(),
+                                    ));
+                                }
+                                Err(err) => {
+                                    let bundle: TheCodeBundle =
+                                        CODEEDITOR.lock().unwrap().get_bundle();
+                                    server_ctx.diagnostics.push(CompileDiagnostic::new(
+                                        bundle.id,
+                                        bundle.name,
+                                        grid,
+                                        &err,
+                                    ));
                                 }
-                            } else {
-                                code_view.set_debug_module(TheDebugModule::new());
                             }
                         }
                     }
+
+                    // Report the errors of the other grids of the bundle, too.
+                    let mut diagnostics = std::mem::take(&mut server_ctx.diagnostics);
+                    diagnostics.append(&mut server.take_diagnostics());
+                    self.show_diagnostics(ui, ctx, server_ctx, diagnostics);
                 }
             }
             TheEvent::CodeBundleChanged(bundle, _) => {
@@ -1021,6 +1241,7 @@ impl Sidebar {
                                 if let Some(code) = project.codes.get_mut(&bundle.id) {
                                     *code = bundle.clone();
                                 }
+                                server.insert_code(bundle.clone());
                                 redraw = true;
                             }
                         }
@@ -1309,8 +1530,46 @@ impl Sidebar {
                         }
                     }
                 }
+                if filter_role == 0 || filter_role == 2 {
+                    // Show Areas
+                    let mut areas: Vec<&Area> = region.areas.values().collect();
+                    areas.sort_by(|a, b| a.name.cmp(&b.name));
+                    for area in areas {
+                        if filter_text.is_empty() || area.name.to_lowercase().contains(&filter_text)
+                        {
+                            let mut item = TheListItem::new(TheId::named_with_id(
+                                "Region Content List Item",
+                                area.id,
+                            ));
+                            item.set_text(area.name.clone());
+                            item.add_value_column(100, TheValue::Text("Area".to_string()));
+                            list.add_item(item, ctx);
+                        }
+                    }
+                }
+                if filter_role == 0 || filter_role == 3 {
+                    // Show Items
+                    for id in region.items.keys() {
+                        let mut name = "Item".to_string();
+                        if let Some(TheValue::Text(text)) =
+                            server.get_item_property(region.id, *id, "name".to_string())
+                        {
+                            name = text;
+                        }
+                        if filter_text.is_empty() || name.to_lowercase().contains(&filter_text) {
+                            let mut item = TheListItem::new(TheId::named_with_id(
+                                "Region Content List Item",
+                                *id,
+                            ));
+                            item.set_text(name);
+                            item.add_value_column(100, TheValue::Text("Item".to_string()));
+                            list.add_item(item, ctx);
+                        }
+                    }
+                }
             }
         }
+        self.apply_area(ui, ctx, None);
 
         if let Some(widget) = ui
             .canvas
@@ -1459,13 +1718,7 @@ impl Sidebar {
                             let mut item =
                                 TheListItem::new(TheId::named_with_id("Tilemap Tile", tile.id));
                             item.set_text(tile.name.clone());
-                            let mut sub_text = if tile.blocking {
-                                "Blocking".to_string()
-                            } else {
-                                "Non-Blocking".to_string()
-                            };
-                            sub_text += ("  ".to_string() + tile.role.to_string()).as_str();
-                            item.set_sub_text(sub_text);
+                            item.set_sub_text(self.tile_sub_text(tile));
                             item.set_size(42);
                             item.set_icon(tile.sequence.regions[0].scale(&tilemap.buffer, 36, 36));
                             list_layout.add_item(item, ctx);
@@ -1512,6 +1765,65 @@ impl Sidebar {
         }
     }
 
+    /// Apply the given area to the UI.
+    pub fn apply_area(&mut self, ui: &mut TheUI, ctx: &mut TheContext, area: Option<&Area>) {
+        ui.set_widget_disabled_state("Area Remove", ctx, area.is_none());
+
+        if let Some(widget) = ui
+            .canvas
+            .get_widget(Some(&"Area Name Edit".to_string()), None)
+        {
+            if let Some(area) = area {
+                widget.set_value(TheValue::Text(area.name.clone()));
+                widget.set_disabled(false);
+            } else {
+                widget.set_value(TheValue::Empty);
+                widget.set_disabled(true);
+            }
+        }
+    }
+
+    /// Shows the first of the given compile errors in the status bar and highlights the
+    /// failing atoms in the code editor.
+    pub fn show_diagnostics(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+        diagnostics: Vec<CompileDiagnostic>,
+    ) {
+        if let Some(first) = diagnostics.first() {
+            let text = if diagnostics.len() > 1 {
+                format!("Compile error: {} (+{} more)", first, diagnostics.len() - 1)
+            } else {
+                format!("Compile error: {}", first)
+            };
+            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), text));
+        }
+
+        server_ctx.diagnostics = diagnostics;
+        self.highlight_diagnostics(ui, server_ctx);
+    }
+
+    /// Highlights the failing atoms of the grid shown in the code editor.
+    pub fn highlight_diagnostics(&mut self, ui: &mut TheUI, server_ctx: &ServerContext) {
+        let grid_id = CODEEDITOR.lock().unwrap().get_codegrid_id(ui);
+
+        let mut debug_module = TheDebugModule::new();
+        for diagnostic in &server_ctx.diagnostics {
+            if diagnostic.grid_id == grid_id {
+                debug_module.values.insert(
+                    diagnostic.location,
+                    (
+                        Some(TheValue::Text(diagnostic.message.clone())),
+                        TheValue::Bool(false),
+                    ),
+                );
+            }
+        }
+        CODEEDITOR.lock().unwrap().set_debug_module(debug_module, ui);
+    }
+
     /// Returns the selected id in the given list layout
     pub fn get_selected_in_list_layout(&self, ui: &mut TheUI, layout_name: &str) -> Option<TheId> {
         if let Some(layout) = ui.canvas.get_layout(Some(&layout_name.to_string()), None) {
@@ -1522,6 +1834,115 @@ impl Sidebar {
         None
     }
 
+    /// Sets the selected tile of the tile list for the mask of the autotile rule set in the
+    /// tilemap editor, or removes it from the rule set.
+    pub fn edit_autotile(
+        &mut self,
+        set: bool,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+    ) {
+        let name = if let Some(edit) = ui.get_text_line_edit("Tilemap Editor Autotile Name") {
+            edit.text().trim().to_string()
+        } else {
+            String::new()
+        };
+
+        let mask: Option<u8> =
+            if let Some(edit) = ui.get_text_line_edit("Tilemap Editor Autotile Mask") {
+                edit.text().trim().parse().ok()
+            } else {
+                None
+            };
+
+        let bits = if let Some(drop_down) = ui.get_drop_down_menu("Tilemap Editor Autotile Bits") {
+            if drop_down.selected_index() == 1 {
+                AutotileBits::Eight
+            } else {
+                AutotileBits::Four
+            }
+        } else {
+            AutotileBits::Four
+        };
+
+        let selected = self.get_selected_in_list_layout(ui, "Tilemap Tile List");
+
+        let status = if let (Some(tile), false) = (selected, name.is_empty()) {
+            if let Some(tilemap) = self
+                .curr_tilemap_uuid
+                .and_then(|uuid| project.get_tilemap(uuid))
+            {
+                if set {
+                    if tilemap.get_autotile_mut(&name).is_none() {
+                        tilemap.autotiles.push(Autotile::new(name.clone(), bits));
+                    }
+                    if let Some(autotile) = tilemap.get_autotile_mut(&name) {
+                        match mask {
+                            Some(mask) if autotile.bits == AutotileBits::Eight || mask < 16 => {
+                                autotile.tiles.insert(mask, tile.uuid);
+                                format!(
+                                    "Set mask {} of the autotile rule set \"{}\" ({} masks).",
+                                    mask,
+                                    name,
+                                    autotile.tiles.len()
+                                )
+                            }
+                            _ => {
+                                "The mask must be 0 to 15 (4 bit) or 0 to 255 (8 bit).".to_string()
+                            }
+                        }
+                    } else {
+                        String::new()
+                    }
+                } else if let Some(autotile) = tilemap.get_autotile_mut(&name) {
+                    autotile.tiles.retain(|_, id| *id != tile.uuid);
+                    tilemap
+                        .autotiles
+                        .retain(|autotile| !autotile.tiles.is_empty());
+                    format!("Removed the tile from the autotile rule set \"{}\".", name)
+                } else {
+                    format!("There is no autotile rule set named \"{}\".", name)
+                }
+            } else {
+                String::new()
+            }
+        } else {
+            "Select a tile and enter the name of the autotile rule set first.".to_string()
+        };
+
+        if !status.is_empty() {
+            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+        }
+    }
+
+    /// Returns the sub text of a tile in the tile list, describing its role and flags.
+    pub fn tile_sub_text(&self, tile: &Tile) -> String {
+        let mut sub_text = if tile.blocking {
+            "Blocking".to_string()
+        } else {
+            "Non-Blocking".to_string()
+        };
+        sub_text += ("  ".to_string() + tile.role.to_string()).as_str();
+
+        if tile.flags.blocks_vision {
+            sub_text += "  Vision";
+        }
+        if tile.flags.water {
+            sub_text += "  Water";
+        }
+        if tile.flags.damage > 0 {
+            sub_text += &format!("  Damage {}", tile.flags.damage);
+        }
+        if tile.flags.movement_cost > 1 {
+            sub_text += &format!("  Cost {}", tile.flags.movement_cost);
+        }
+        if tile.flags.light > 0 {
+            sub_text += &format!("  Light {}", tile.flags.light);
+        }
+        sub_text
+    }
+
     /// Deselects all items in the given list layout.
     pub fn deselect_all(&self, layout_name: &str, ui: &mut TheUI) {
         if let Some(layout) = ui.canvas.get_layout(Some(&layout_name.to_string()), None) {
@@ -7,6 +7,11 @@ enum EditorMode {
     Pick,
     Erase,
     Select,
+    Rect,
+    Outline,
+    Line,
+    Fill,
+    Stamp,
 }
 
 pub struct TileEditor {
@@ -20,6 +25,16 @@ pub struct TileEditor {
 
     icon_normal_border_color: RGBA,
     icon_selected_border_color: RGBA,
+
+    /// The changes of the current brush stroke, added to the undo stack as one step when the
+    /// stroke ends.
+    stroke: Option<RegionUndo>,
+
+    /// The first corner or end point of the rectangle and line tools.
+    shape_start: Option<Vec2i>,
+
+    /// The copied selection or loaded prefab, pasted by the stamp tool.
+    clipboard: Option<Prefab>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -34,6 +49,10 @@ impl TileEditor {
 
             curr_layer_role: Layer2DRole::Ground,
 
+            stroke: None,
+            shape_start: None,
+            clipboard: None,
+
             icon_normal_border_color: [100, 100, 100, 255],
             icon_selected_border_color: [255, 255, 255, 255],
         }
@@ -183,13 +202,98 @@ impl TileEditor {
             "Select an area in the region.".to_string(),
             "selection".to_string(),
         );
+        gb.add_text_status_icon(
+            "Rect".to_string(),
+            "Fill a rectangle with the current tile. Click the two corners.".to_string(),
+            "selection".to_string(),
+        );
+        gb.add_text_status_icon(
+            "Outline".to_string(),
+            "Draw the outline of a rectangle with the current tile. Click the two corners."
+                .to_string(),
+            "selection".to_string(),
+        );
+        gb.add_text_status_icon(
+            "Line".to_string(),
+            "Draw a line with the current tile. Click the two end points.".to_string(),
+            "draw".to_string(),
+        );
+        gb.add_text_status_icon(
+            "Fill".to_string(),
+            "Flood fill the connected area of matching tiles in the current layer.".to_string(),
+            "draw".to_string(),
+        );
+        gb.add_text_status_icon(
+            "Stamp".to_string(),
+            "Paste the copied selection or the loaded prefab at the clicked position.".to_string(),
+            "draw".to_string(),
+        );
         gb.set_item_width(65);
 
+        let mut copy_button = TheTraybarButton::new(TheId::named("Region Editor Copy"));
+        copy_button.set_text("Copy".to_string());
+        copy_button.set_status_text("Copy the selection in the region.");
+
+        let mut cut_button = TheTraybarButton::new(TheId::named("Region Editor Cut"));
+        cut_button.set_text("Cut".to_string());
+        cut_button.set_status_text("Copy the selection in the region and delete its content.");
+
+        let mut characters_text = TheText::new(TheId::empty());
+        characters_text.set_text("Characters".to_string());
+        let characters_check_button =
+            TheCheckButton::new(TheId::named("Region Editor Copy Characters"));
+
+        let mut rotate_button = TheTraybarButton::new(TheId::named("Region Editor Rotate"));
+        rotate_button.set_text("Rotate".to_string());
+        rotate_button.set_status_text("Rotate the copied selection clockwise.");
+
+        let mut flip_h_button = TheTraybarButton::new(TheId::named("Region Editor Flip H"));
+        flip_h_button.set_text("Flip H".to_string());
+        flip_h_button.set_status_text("Mirror the copied selection horizontally.");
+
+        let mut flip_v_button = TheTraybarButton::new(TheId::named("Region Editor Flip V"));
+        flip_v_button.set_text("Flip V".to_string());
+        flip_v_button.set_status_text("Mirror the copied selection vertically.");
+
+        let mut prefab_name_edit = TheTextLineEdit::new(TheId::named("Region Editor Prefab Name"));
+        prefab_name_edit.limiter_mut().set_max_width(100);
+        prefab_name_edit.set_status_text("The name of the prefab to save or load.");
+
+        let mut save_prefab_button =
+            TheTraybarButton::new(TheId::named("Region Editor Save Prefab"));
+        save_prefab_button.set_text("Save".to_string());
+        save_prefab_button.set_status_text("Save the copied selection as a prefab.");
+
+        let mut load_prefab_button =
+            TheTraybarButton::new(TheId::named("Region Editor Load Prefab"));
+        load_prefab_button.set_text("Load".to_string());
+        load_prefab_button.set_status_text("Load the prefab for stamping.");
+
         let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
         toolbar_hlayout.set_background_color(None);
         toolbar_hlayout.set_margin(vec4i(5, 4, 5, 4));
         toolbar_hlayout.add_widget(Box::new(gb));
 
+        let mut hdivider = TheHDivider::new(TheId::empty());
+        hdivider.limiter_mut().set_max_width(15);
+        toolbar_hlayout.add_widget(Box::new(hdivider));
+
+        toolbar_hlayout.add_widget(Box::new(copy_button));
+        toolbar_hlayout.add_widget(Box::new(cut_button));
+        toolbar_hlayout.add_widget(Box::new(characters_text));
+        toolbar_hlayout.add_widget(Box::new(characters_check_button));
+        toolbar_hlayout.add_widget(Box::new(rotate_button));
+        toolbar_hlayout.add_widget(Box::new(flip_h_button));
+        toolbar_hlayout.add_widget(Box::new(flip_v_button));
+
+        let mut hdivider = TheHDivider::new(TheId::empty());
+        hdivider.limiter_mut().set_max_width(15);
+        toolbar_hlayout.add_widget(Box::new(hdivider));
+
+        toolbar_hlayout.add_widget(Box::new(prefab_name_edit));
+        toolbar_hlayout.add_widget(Box::new(save_prefab_button));
+        toolbar_hlayout.add_widget(Box::new(load_prefab_button));
+
         bottom_toolbar.set_layout(toolbar_hlayout);
         center.set_bottom(bottom_toolbar);
 
@@ -246,6 +350,8 @@ impl TileEditor {
                                     {
                                         w.renderer_mut().set_region(region);
                                         w.renderer_mut().set_textures(project.extract_tiles());
+                                        w.renderer_mut()
+                                            .set_light_map(server.light_map(&region.id));
                                     }
                                 }
                             }
@@ -261,7 +367,18 @@ impl TileEditor {
                         self.editor_mode = EditorMode::Erase;
                     } else if *index == 3 {
                         self.editor_mode = EditorMode::Select;
+                    } else if *index == 4 {
+                        self.editor_mode = EditorMode::Rect;
+                    } else if *index == 5 {
+                        self.editor_mode = EditorMode::Outline;
+                    } else if *index == 6 {
+                        self.editor_mode = EditorMode::Line;
+                    } else if *index == 7 {
+                        self.editor_mode = EditorMode::Fill;
+                    } else if *index == 8 {
+                        self.editor_mode = EditorMode::Stamp;
                     }
+                    self.shape_start = None;
 
                     if *SIDEBARMODE.lock().unwrap() == SidebarMode::Region {
                         ctx.ui.send(TheEvent::Custom(
@@ -332,7 +449,27 @@ impl TileEditor {
                 }
             }*/
             TheEvent::TileEditorClicked(_id, coord) | TheEvent::TileEditorDragged(_id, coord) => {
-                if self.editor_mode == EditorMode::Erase {
+                // A click starts a new brush stroke.
+                if let TheEvent::TileEditorClicked(_, _) = event {
+                    self.finish_stroke(ctx);
+                }
+
+                if matches!(
+                    self.editor_mode,
+                    EditorMode::Rect | EditorMode::Outline | EditorMode::Line | EditorMode::Fill
+                ) {
+                    // The shape tools work on clicks only, dragging is ignored.
+                    if let TheEvent::TileEditorClicked(_, _) = event {
+                        redraw =
+                            self.apply_shape_tool(*coord, ui, ctx, project, server, server_ctx);
+                    }
+                } else if self.editor_mode == EditorMode::Stamp {
+                    if let TheEvent::TileEditorClicked(_, _) = event {
+                        redraw = self.stamp(*coord, ui, ctx, project, server, server_ctx);
+                    }
+                } else if self.editor_mode == EditorMode::Erase {
+                    let autotiles = project.extract_autotiles();
+
                     // If there is a character instance at the position we delete the instance.
                     if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
                         if let Some(c) =
@@ -340,7 +477,12 @@ impl TileEditor {
                         {
                             // Delete the character at the given position.
 
-                            if region.characters.remove(&c.0).is_some() {
+                            if let Some(character) = region.characters.remove(&c.0) {
+                                self.stroke_mut(region.id, ctx).record_character(
+                                    c.0,
+                                    Some(character),
+                                    None,
+                                );
                                 server.remove_character_instance(region.id, c.0);
                                 server_ctx.curr_character_instance = None;
                                 server_ctx.curr_character = None;
@@ -355,17 +497,49 @@ impl TileEditor {
                                     ));
                                 }
                             }
+                        } else if let Some(i) =
+                            server.get_item_at(server_ctx.curr_region, vec2i(coord.x, coord.y))
+                        {
+                            // Delete the item at the given position.
+
+                            if let Some(item) = region.items.remove(&i.0) {
+                                self.stroke_mut(region.id, ctx)
+                                    .record_item(i.0, Some(item), None);
+                                server.remove_item_instance(region.id, i.0);
+                                redraw = true;
+                                self.redraw_region(ui, server, ctx, server_ctx);
+
+                                // Remove from the content list
+                                if let Some(list) = ui.get_list_layout("Region Content List") {
+                                    list.remove(TheId::named_with_id(
+                                        "Region Content List Item",
+                                        i.0,
+                                    ));
+                                }
+                            }
                         } else {
-                            // Delete the tile at the given position.
-                            let mut undo = TheUndo::new(TheId::named("RegionChanged"));
-                            undo.set_undo_data(region.to_json());
+                            // Delete the tile at the given position and update the autotile
+                            // variants of its neighbors.
+                            let positions = region.autotile_neighborhood(&[(coord.x, coord.y)]);
+                            let before: Vec<Option<RegionTile>> = positions
+                                .iter()
+                                .map(|p| region.tiles.get(p).cloned())
+                                .collect();
+
+                            if let Some(tile) = region.tiles.remove(&(coord.x, coord.y)) {
+                                self.stroke_mut(region.id, ctx).record_tile(
+                                    (coord.x, coord.y),
+                                    Some(tile),
+                                    None,
+                                );
+                            }
+                            region.update_autotiles(&positions, &autotiles);
 
-                            if region.tiles.contains_key(&(coord.x, coord.y)) {
-                                region.tiles.remove(&(coord.x, coord.y));
+                            let stroke = self.stroke_mut(region.id, ctx);
+                            for (pos, before) in positions.iter().zip(before) {
+                                stroke.record_tile(*pos, before, region.tiles.get(pos).cloned());
                             }
 
-                            undo.set_redo_data(region.to_json());
-                            ctx.ui.undo_stack.add(undo);
                             server.update_region(region);
                             self.set_icon_previews(region, *coord, ui);
                             self.redraw_region(ui, server, ctx, server_ctx);
@@ -427,16 +601,35 @@ impl TileEditor {
                 } else if self.editor_mode == EditorMode::Draw {
                     if let Some(curr_tile_uuid) = self.curr_tile_uuid {
                         if self.tiledrawer.tiles.contains_key(&curr_tile_uuid) {
+                            let autotiles = project.extract_autotiles();
                             if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
-                                let mut undo = TheUndo::new(TheId::named("RegionChanged"));
-                                undo.set_undo_data(region.to_json());
+                                // Set the tile and update the autotile variants of the tile and
+                                // its neighbors.
+                                let mut positions =
+                                    region.autotile_neighborhood(&[(coord.x, coord.y)]);
+                                if !positions.contains(&(coord.x, coord.y)) {
+                                    positions.push((coord.x, coord.y));
+                                }
+                                let before: Vec<Option<RegionTile>> = positions
+                                    .iter()
+                                    .map(|p| region.tiles.get(p).cloned())
+                                    .collect();
 
                                 region.set_tile(
                                     (coord.x, coord.y),
                                     self.curr_layer_role,
                                     self.curr_tile_uuid,
                                 );
-                                undo.set_redo_data(region.to_json());
+                                region.update_autotiles(&positions, &autotiles);
+
+                                let stroke = self.stroke_mut(region.id, ctx);
+                                for (pos, before) in positions.iter().zip(before) {
+                                    stroke.record_tile(
+                                        *pos,
+                                        before,
+                                        region.tiles.get(pos).cloned(),
+                                    );
+                                }
                                 self.set_icon_previews(region, *coord, ui);
 
                                 server.update_region(region);
@@ -450,10 +643,10 @@ impl TileEditor {
                                         })
                                     {
                                         w.renderer_mut().set_region(region);
+                                        w.renderer_mut()
+                                            .set_light_map(server.light_map(&region.id));
                                     }
                                 }
-
-                                ctx.ui.undo_stack.add(undo);
                             }
                         }
                         self.redraw_region(ui, server, ctx, server_ctx);
@@ -461,6 +654,9 @@ impl TileEditor {
                 }
             }
             TheEvent::TileEditorHoverChanged(_id, coord) => {
+                // Hovering without a pressed button ends the brush stroke.
+                self.finish_stroke(ctx);
+
                 if let Some(text) = ui.get_text("Cursor Position") {
                     text.set_text(format!("({}, {})", coord.x, coord.y));
                     redraw = true;
@@ -524,6 +720,25 @@ impl TileEditor {
                                 region.scroll_offset = vec2i(p.x as i32 * region.grid_size, p.y as i32 * region.grid_size);
                             }
                         }
+                    } else if let Some(area) = project
+                        .get_region(&server_ctx.curr_region)
+                        .and_then(|region| region.areas.get(&id.uuid))
+                    {
+                        // If it's an area, show it as the selection in the region editor.
+                        self.editor_mode = EditorMode::Select;
+                        if let Some(button) = ui.get_group_button("Editor Group") {
+                            button.set_index(3);
+                        }
+
+                        if let Some(rgba_layout) = ui.get_rgba_layout("Region Editor") {
+                            if let Some(rgba_view) = rgba_layout.rgba_view_mut().as_rgba_view() {
+                                rgba_view.set_selection(area.area.clone());
+                            }
+                            if let Some(pos) = area.area.iter().min() {
+                                rgba_layout.scroll_to_grid(vec2i(pos.0, pos.1));
+                            }
+                        }
+                        redraw = true;
                     }
                 }
                 // Region Selection
@@ -558,6 +773,7 @@ impl TileEditor {
                                     })
                                 {
                                     w.renderer_mut().set_region(r);
+                                    w.renderer_mut().set_light_map(server.light_map(&r.id));
                                 }
                             }
                             server_ctx.curr_region = r.id;
@@ -575,9 +791,87 @@ impl TileEditor {
                             icon_view.set_rgba_tile(t.clone());
                         }
                     }
+                } else if id.name == "Region Editor Copy" || id.name == "Region Editor Cut" {
+                    self.copy_selection(
+                        id.name == "Region Editor Cut",
+                        ui,
+                        ctx,
+                        project,
+                        server,
+                        server_ctx,
+                    );
+                    redraw = true;
+                } else if id.name == "Region Editor Rotate"
+                    || id.name == "Region Editor Flip H"
+                    || id.name == "Region Editor Flip V"
+                {
+                    if let Some(clipboard) = &mut self.clipboard {
+                        if id.name == "Region Editor Rotate" {
+                            clipboard.rotate();
+                        } else if id.name == "Region Editor Flip H" {
+                            clipboard.flip_horizontally();
+                        } else {
+                            clipboard.flip_vertically();
+                        }
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            format!(
+                                "The copied selection is now {} x {} tiles.",
+                                clipboard.width, clipboard.height
+                            ),
+                        ));
+                    } else {
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            "Copy a selection or load a prefab first.".to_string(),
+                        ));
+                    }
+                } else if id.name == "Region Editor Save Prefab" {
+                    let name = self.prefab_name(ui);
+                    if name.is_empty() {
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            "Enter the name of the prefab first.".to_string(),
+                        ));
+                    } else if let Some(clipboard) = &self.clipboard {
+                        let mut prefab = clipboard.clone();
+                        prefab.id = Uuid::new_v4();
+                        prefab.name = name.clone();
+                        project.add_prefab(prefab);
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            format!("Saved the prefab \"{}\".", name),
+                        ));
+                    } else {
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            "Copy a selection first.".to_string(),
+                        ));
+                    }
+                } else if id.name == "Region Editor Load Prefab" {
+                    let name = self.prefab_name(ui);
+                    if let Some(prefab) = project.get_prefab_by_name(&name) {
+                        self.clipboard = Some(prefab.clone());
+                        self.set_stamp_mode(ui, ctx);
+                        redraw = true;
+                    } else {
+                        let names: Vec<String> = project
+                            .sorted_prefab_list()
+                            .into_iter()
+                            .map(|(_, name)| name)
+                            .collect();
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            format!(
+                                "There is no prefab named \"{}\". Available prefabs: {}.",
+                                name,
+                                names.join(", ")
+                            ),
+                        ));
+                    }
                 } else if id.name == "Tilemap Editor Add Selection" {
                     self.tiledrawer.tiles = project.extract_tiles();
-                    server.update_tiles(project.extract_tiles());
+                    server.update_tiles(project.extract_tiles(), project.extract_tile_flags());
                 } else if id.name == "Ground Icon" {
                     self.curr_layer_role = Layer2DRole::Ground;
                     self.set_icon_colors(ui);
@@ -708,6 +1002,310 @@ impl TileEditor {
         // }
     }
 
+    /// Handles a click of the rectangle, outline, line and flood fill tools. The first click of
+    /// the rectangle and line tools sets the start, the second click paints the shape.
+    fn apply_shape_tool(
+        &mut self,
+        coord: Vec2i,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server: &mut Server,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        if let Some(curr_tile_uuid) = self.curr_tile_uuid {
+            if !self.tiledrawer.tiles.contains_key(&curr_tile_uuid) {
+                return false;
+            }
+        } else {
+            ctx.ui.send(TheEvent::SetStatusText(
+                TheId::empty(),
+                "Select a tile to paint with first.".to_string(),
+            ));
+            return false;
+        }
+
+        let positions = if let Some(region) = project.get_region(&server_ctx.curr_region) {
+            match self.editor_mode {
+                EditorMode::Fill => region.flood_fill_positions(coord, self.curr_layer_role),
+                _ => {
+                    if let Some(start) = self.shape_start.take() {
+                        if self.editor_mode == EditorMode::Line {
+                            Region::line_positions(start, coord)
+                        } else {
+                            Region::rect_positions(
+                                start,
+                                coord,
+                                self.editor_mode == EditorMode::Outline,
+                            )
+                        }
+                    } else {
+                        self.shape_start = Some(coord);
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            format!(
+                                "Start set to ({}, {}). Click the end point.",
+                                coord.x, coord.y
+                            ),
+                        ));
+                        return false;
+                    }
+                }
+            }
+        } else {
+            return false;
+        };
+
+        self.paint_tiles(&positions, ui, ctx, project, server, server_ctx);
+        true
+    }
+
+    /// Sets the current tile in the current layer at the given positions inside the region and
+    /// updates the autotile variants, adding all changes to the undo stack as one step.
+    fn paint_tiles(
+        &mut self,
+        positions: &[(i32, i32)],
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server: &mut Server,
+        server_ctx: &mut ServerContext,
+    ) {
+        let autotiles = project.extract_autotiles();
+        if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
+            let mut region_undo = RegionUndo::new(region.id);
+
+            let neighborhood = region.autotile_neighborhood(positions);
+            let before: Vec<Option<RegionTile>> = neighborhood
+                .iter()
+                .map(|p| region.tiles.get(p).cloned())
+                .collect();
+
+            for (x, y) in positions {
+                if region.contains(vec2i(*x, *y)) {
+                    region.set_tile((*x, *y), self.curr_layer_role, self.curr_tile_uuid);
+                }
+            }
+            region.update_autotiles(&neighborhood, &autotiles);
+
+            for (pos, before) in neighborhood.iter().zip(before) {
+                region_undo.record_tile(*pos, before, region.tiles.get(pos).cloned());
+            }
+
+            self.finish_edit(region_undo, ui, ctx, project, server, server_ctx);
+        }
+    }
+
+    /// Copies the selection of the region editor into the clipboard and switches to the stamp
+    /// tool. When cutting, the copied tiles and characters are deleted as one undo step.
+    fn copy_selection(
+        &mut self,
+        cut: bool,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server: &mut Server,
+        server_ctx: &mut ServerContext,
+    ) {
+        let mut selection = FxHashSet::default();
+        if let Some(rgba_layout) = ui.get_rgba_layout("Region Editor") {
+            if let Some(rgba_view) = rgba_layout.rgba_view_mut().as_rgba_view() {
+                selection = rgba_view.selection();
+            }
+        }
+
+        let characters = if let Some(widget) = ui
+            .canvas
+            .get_widget(Some(&"Region Editor Copy Characters".to_string()), None)
+        {
+            widget.state() == TheWidgetState::Selected
+        } else {
+            false
+        };
+
+        let prefab = if let Some(region) = project.get_region(&server_ctx.curr_region) {
+            Prefab::from_selection(region, &selection, characters)
+        } else {
+            None
+        };
+
+        if let Some(prefab) = prefab {
+            if cut {
+                if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
+                    let mut region_undo = RegionUndo::new(region.id);
+                    let mut positions: Vec<&(i32, i32)> = selection.iter().collect();
+                    positions.sort();
+                    for pos in positions {
+                        if let Some(tile) = region.tiles.remove(pos) {
+                            region_undo.record_tile(*pos, Some(tile), None);
+                        }
+                    }
+                    for character in &prefab.characters {
+                        if let Some(character) = region.characters.remove(&character.id) {
+                            region_undo.record_character(character.id, Some(character), None);
+                        }
+                    }
+                    self.finish_edit(region_undo, ui, ctx, project, server, server_ctx);
+                }
+            }
+            self.clipboard = Some(prefab);
+            self.set_stamp_mode(ui, ctx);
+        } else {
+            ctx.ui.send(TheEvent::SetStatusText(
+                TheId::empty(),
+                "Select an area in the region editor first.".to_string(),
+            ));
+        }
+    }
+
+    /// Pastes the clipboard into the region with its top left at the given position.
+    fn stamp(
+        &mut self,
+        coord: Vec2i,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server: &mut Server,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        if let Some(clipboard) = &self.clipboard {
+            if let Some(region) = project.get_region_mut(&server_ctx.curr_region) {
+                let region_undo = clipboard.stamp(region, coord);
+                self.finish_edit(region_undo, ui, ctx, project, server, server_ctx);
+                return true;
+            }
+        } else {
+            ctx.ui.send(TheEvent::SetStatusText(
+                TheId::empty(),
+                "Copy a selection or load a prefab first.".to_string(),
+            ));
+        }
+        false
+    }
+
+    /// Switches to the stamp tool after the clipboard changed.
+    fn set_stamp_mode(&mut self, ui: &mut TheUI, ctx: &mut TheContext) {
+        self.editor_mode = EditorMode::Stamp;
+        self.shape_start = None;
+        if let Some(button) = ui.get_group_button("Editor Group") {
+            button.set_index(8);
+        }
+        if let Some(clipboard) = &self.clipboard {
+            ctx.ui.send(TheEvent::SetStatusText(
+                TheId::empty(),
+                format!(
+                    "Click in the region to paste the {} tiles and {} characters.",
+                    clipboard.tiles.len(),
+                    clipboard.characters.len()
+                ),
+            ));
+        }
+    }
+
+    /// Returns the text of the prefab name edit.
+    fn prefab_name(&self, ui: &mut TheUI) -> String {
+        if let Some(edit) = ui.get_text_line_edit("Region Editor Prefab Name") {
+            edit.text().trim().to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Adds an edit of the current region to the undo stack as one step, and updates the server,
+    /// the content list and the views. The edit must already be applied to the project.
+    fn finish_edit(
+        &mut self,
+        region_undo: RegionUndo,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server: &mut Server,
+        server_ctx: &mut ServerContext,
+    ) {
+        if region_undo.is_empty() {
+            return;
+        }
+
+        if let Some(region) = project.get_region(&region_undo.region_id) {
+            server.update_region(region);
+
+            for (id, _, after) in &region_undo.characters {
+                if let Some(character) = after {
+                    server.add_character_instance_to_region(region.id, character.clone());
+
+                    let mut name = "Character".to_string();
+                    if let Some(bundle) = project.characters.get(&character.character_id) {
+                        name = bundle.name.clone();
+                    }
+                    if let Some(list) = ui.get_list_layout("Region Content List") {
+                        let mut item =
+                            TheListItem::new(TheId::named_with_id("Region Content List Item", *id));
+                        item.set_text(name);
+                        item.add_value_column(100, TheValue::Text("Character".to_string()));
+                        list.add_item(item, ctx);
+                    }
+                } else {
+                    server.remove_character_instance(region.id, *id);
+                    if server_ctx.curr_character_instance == Some(*id) {
+                        server_ctx.curr_character_instance = None;
+                        server_ctx.curr_character = None;
+                    }
+                    if let Some(list) = ui.get_list_layout("Region Content List") {
+                        list.remove(TheId::named_with_id("Region Content List Item", *id));
+                    }
+                }
+            }
+
+            if let Some(widget) = ui.get_widget("RenderView") {
+                if let Some(w) = widget
+                    .as_any()
+                    .downcast_mut::<TheRenderView>()
+                    .map(|external_widget| external_widget as &mut dyn TheRenderViewTrait)
+                {
+                    w.renderer_mut().set_region(region);
+                    w.renderer_mut().set_light_map(server.light_map(&region.id));
+                }
+            }
+        }
+
+        self.add_undo(region_undo, ctx);
+        self.redraw_region(ui, server, ctx, server_ctx);
+    }
+
+    /// Returns the changes of the current brush stroke in the given region, starting a new
+    /// stroke if needed.
+    fn stroke_mut(&mut self, region_id: Uuid, ctx: &mut TheContext) -> &mut RegionUndo {
+        if let Some(stroke) = &self.stroke {
+            if stroke.region_id != region_id {
+                self.finish_stroke(ctx);
+            }
+        }
+        self.stroke.get_or_insert_with(|| RegionUndo::new(region_id))
+    }
+
+    /// Ends the current brush stroke and adds its changes to the undo stack as one step.
+    pub fn finish_stroke(&mut self, ctx: &mut TheContext) {
+        if let Some(stroke) = self.stroke.take() {
+            Self::push_undo(stroke, ctx);
+        }
+    }
+
+    /// Adds the changes of a region edit to the undo stack. Ends the current brush stroke.
+    pub fn add_undo(&mut self, region_undo: RegionUndo, ctx: &mut TheContext) {
+        self.finish_stroke(ctx);
+        Self::push_undo(region_undo, ctx);
+    }
+
+    /// Adds the changes of a region edit as one step to the undo stack.
+    fn push_undo(region_undo: RegionUndo, ctx: &mut TheContext) {
+        if !region_undo.is_empty() {
+            let mut undo = TheUndo::new(TheId::named("RegionEdited"));
+            undo.set_undo_data(region_undo.inverted().to_json());
+            undo.set_redo_data(region_undo.to_json());
+            ctx.ui.undo_stack.add(undo);
+        }
+    }
+
     /// Redraw the map of the current region on tick.
     pub fn redraw_region(
         &mut self,
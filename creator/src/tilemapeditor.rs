@@ -43,6 +43,38 @@ impl TilemapEditor {
 
         let block_check_button = TheCheckButton::new(TheId::named("Tilemap Editor Block"));
 
+        let mut vision_text = TheText::new(TheId::empty());
+        vision_text.set_text("Blocks Vision".to_string());
+        let vision_check_button = TheCheckButton::new(TheId::named("Tilemap Editor Vision"));
+
+        let mut water_text = TheText::new(TheId::empty());
+        water_text.set_text("Water".to_string());
+        let water_check_button = TheCheckButton::new(TheId::named("Tilemap Editor Water"));
+
+        let mut damage_text = TheText::new(TheId::empty());
+        damage_text.set_text("Damage".to_string());
+        let mut damage_edit = TheTextLineEdit::new(TheId::named("Tilemap Editor Damage"));
+        damage_edit.set_text("0".to_string());
+        damage_edit.set_range(TheValue::RangeI32(0..=100));
+        damage_edit.limiter_mut().set_max_width(30);
+        damage_edit.set_status_text("The hit points lost per tick by characters on the tile.");
+
+        let mut cost_text = TheText::new(TheId::empty());
+        cost_text.set_text("Cost".to_string());
+        let mut cost_edit = TheTextLineEdit::new(TheId::named("Tilemap Editor Cost"));
+        cost_edit.set_text("1".to_string());
+        cost_edit.set_range(TheValue::RangeI32(1..=10));
+        cost_edit.limiter_mut().set_max_width(30);
+        cost_edit.set_status_text("The number of ticks needed to cross the tile.");
+
+        let mut light_text = TheText::new(TheId::empty());
+        light_text.set_text("Light".to_string());
+        let mut light_edit = TheTextLineEdit::new(TheId::named("Tilemap Editor Light"));
+        light_edit.set_text("0".to_string());
+        light_edit.set_range(TheValue::RangeI32(0..=20));
+        light_edit.limiter_mut().set_max_width(30);
+        light_edit.set_status_text("The radius of the light emitted by the tile.");
+
         let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
         toolbar_hlayout.set_background_color(None);
         toolbar_hlayout.set_margin(vec4i(10, 4, 5, 4));
@@ -67,6 +99,16 @@ impl TilemapEditor {
 
         toolbar_hlayout.add_widget(Box::new(block_name_text));
         toolbar_hlayout.add_widget(Box::new(block_check_button));
+        toolbar_hlayout.add_widget(Box::new(vision_text));
+        toolbar_hlayout.add_widget(Box::new(vision_check_button));
+        toolbar_hlayout.add_widget(Box::new(water_text));
+        toolbar_hlayout.add_widget(Box::new(water_check_button));
+        toolbar_hlayout.add_widget(Box::new(damage_text));
+        toolbar_hlayout.add_widget(Box::new(damage_edit));
+        toolbar_hlayout.add_widget(Box::new(cost_text));
+        toolbar_hlayout.add_widget(Box::new(cost_edit));
+        toolbar_hlayout.add_widget(Box::new(light_text));
+        toolbar_hlayout.add_widget(Box::new(light_edit));
 
         let mut hdivider = TheHDivider::new(TheId::empty());
         hdivider.limiter_mut().set_max_width(15);
@@ -86,6 +128,58 @@ impl TilemapEditor {
         toolbar_canvas.set_layout(toolbar_hlayout);
         canvas.set_top(toolbar_canvas);
 
+        // Autotile rule sets
+
+        let mut autotile_canvas = TheCanvas::new();
+        autotile_canvas.set_widget(TheTraybar::new(TheId::empty()));
+
+        let mut autotile_text = TheText::new(TheId::empty());
+        autotile_text.set_text("Autotile".to_string());
+
+        let mut autotile_name_edit =
+            TheTextLineEdit::new(TheId::named("Tilemap Editor Autotile Name"));
+        autotile_name_edit.limiter_mut().set_max_width(120);
+        autotile_name_edit.set_status_text("The name of the autotile rule set.");
+
+        let mut bits_drop_down = TheDropdownMenu::new(TheId::named("Tilemap Editor Autotile Bits"));
+        bits_drop_down.add_option("4 Bit".to_string());
+        bits_drop_down.add_option("8 Bit".to_string());
+        bits_drop_down.set_status_text(
+            "4 Bit rule sets check the sides (N 1, E 2, S 4, W 8), 8 Bit rule sets also the corners (N 1, NE 2, E 4, SE 8, S 16, SW 32, W 64, NW 128).",
+        );
+
+        let mut mask_text = TheText::new(TheId::empty());
+        mask_text.set_text("Mask".to_string());
+        let mut mask_edit = TheTextLineEdit::new(TheId::named("Tilemap Editor Autotile Mask"));
+        mask_edit.set_text("0".to_string());
+        mask_edit.set_range(TheValue::RangeI32(0..=255));
+        mask_edit.limiter_mut().set_max_width(40);
+        mask_edit.set_status_text("The sum of the matching neighbors for the selected tile.");
+
+        let mut set_button = TheTraybarButton::new(TheId::named("Tilemap Editor Autotile Set"));
+        set_button.set_text("Set Mask".to_string());
+        set_button.set_status_text("Use the selected tile for the mask in the autotile rule set.");
+
+        let mut remove_button =
+            TheTraybarButton::new(TheId::named("Tilemap Editor Autotile Remove"));
+        remove_button.set_text("Remove".to_string());
+        remove_button.set_status_text("Remove the selected tile from the autotile rule set.");
+
+        let mut autotile_hlayout = TheHLayout::new(TheId::empty());
+        autotile_hlayout.set_background_color(None);
+        autotile_hlayout.set_margin(vec4i(10, 4, 5, 4));
+
+        autotile_hlayout.add_widget(Box::new(autotile_text));
+        autotile_hlayout.add_widget(Box::new(autotile_name_edit));
+        autotile_hlayout.add_widget(Box::new(bits_drop_down));
+        autotile_hlayout.add_widget(Box::new(mask_text));
+        autotile_hlayout.add_widget(Box::new(mask_edit));
+        autotile_hlayout.add_widget(Box::new(set_button));
+        autotile_hlayout.add_widget(Box::new(remove_button));
+
+        autotile_canvas.set_layout(autotile_hlayout);
+        canvas.set_bottom(autotile_canvas);
+
         canvas
     }
 
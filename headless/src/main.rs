@@ -0,0 +1,480 @@
+use shared::prelude::*;
+use std::io::Write;
+use std::path::PathBuf;
+use theframework::prelude::*;
+
+const USAGE: &str = "Usage: eldiron-headless <project.eldiron> [options]
+
+Options:
+  --ticks <n>                  Maximum number of ticks to run (default 100).
+  --seed <n>                   Seed of the simulation (default: random). Runs with the same seed are identical.
+  --until <name> <x> <y>       Stop as soon as the character <name> is at the given position.
+  --dump-every <n>             Dump the region updates every n ticks (default: only at the end).
+  --output <file>              Write the dumps to the given file instead of stdout.
+  --restore <file>             Continue from a snapshot (savegame) of a previous run.
+  --snapshot <file>            Write a snapshot (savegame) of the final server state to the given file.
+  --input <file>               Replay the player actions of an input log (one JSON event per line).
+  --record <file>              Write the input log of this run, including the one of a restored snapshot, to the given file.";
+
+/// The command line options of the runner.
+struct Options {
+    project_path: PathBuf,
+    ticks: i64,
+    seed: Option<u64>,
+    until: Option<(String, Vec2i)>,
+    dump_every: Option<i64>,
+    output: Option<PathBuf>,
+    restore: Option<PathBuf>,
+    snapshot: Option<PathBuf>,
+    input: Option<PathBuf>,
+    record: Option<PathBuf>,
+}
+
+/// The state of a single region as written to the dump.
+#[derive(Serialize)]
+struct RegionDump {
+    id: Uuid,
+    name: String,
+    update: RegionUpdate,
+}
+
+/// One dump of all region states at the given tick.
+#[derive(Serialize)]
+struct TickDump {
+    tick: i64,
+    regions: Vec<RegionDump>,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let contents = match std::fs::read_to_string(&options.project_path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            eprintln!(
+                "Unable to read project {}: {}",
+                options.project_path.display(),
+                err
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let project: Project = match serde_json::from_str(&contents) {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("Unable to parse project: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let mut output: Box<dyn Write> = if let Some(path) = &options.output {
+        match std::fs::File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => {
+                eprintln!("Unable to create {}: {}", path.display(), err);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        Box::new(std::io::stdout())
+    };
+
+    let mut server = Server::new();
+    if let Some(seed) = options.seed {
+        server.set_seed(seed);
+    }
+    server.set_project(project.clone());
+    for diagnostic in server.take_diagnostics() {
+        eprintln!("Compile error: {}", diagnostic);
+    }
+
+    if let Some(path) = &options.restore {
+        let snapshot = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| ServerSnapshot::from_json(&json));
+        if let Some(snapshot) = snapshot {
+            server.restore_snapshot(snapshot);
+        } else {
+            eprintln!("Unable to read snapshot {}.", path.display());
+            std::process::exit(2);
+        }
+    }
+
+    let mut input: Vec<InputEvent> = vec![];
+    if let Some(path) = &options.input {
+        if let Ok(contents) = std::fs::read_to_string(path) {
+            for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+                match serde_json::from_str(line) {
+                    Ok(event) => input.push(event),
+                    Err(err) => {
+                        eprintln!("Unable to parse input event \"{}\": {}", line, err);
+                        std::process::exit(2);
+                    }
+                }
+            }
+        } else {
+            eprintln!("Unable to read input log {}.", path.display());
+            std::process::exit(2);
+        }
+        input.sort_by_key(|event| event.tick);
+
+        // All characters of the input log are controlled by players.
+        for event in &input {
+            server.set_player(event.character, true);
+        }
+    }
+
+    server.start();
+
+    eprintln!("Running with seed {}.", server.seed());
+
+    // The condition may already be met before the first tick.
+    let mut condition_met = if let Some((name, position)) = &options.until {
+        is_character_at(&server, name, *position)
+    } else {
+        false
+    };
+
+    // Ticks are counted from the start of this run, a restored snapshot may already be ahead.
+    let start_tick = server.world.tick_counter;
+    let end_tick = start_tick + options.ticks;
+
+    let mut next_dump = options.dump_every.map(|every| start_tick + every);
+
+    // Actions received before a restored snapshot was taken are part of its state already.
+    let mut next_input = input
+        .iter()
+        .take_while(|event| event.tick < start_tick)
+        .count();
+
+    while !condition_met && server.world.tick_counter < end_tick {
+        // Pass the player actions received at this tick.
+        while next_input < input.len() && input[next_input].tick <= server.world.tick_counter {
+            let event = &input[next_input];
+            server.player_action(event.character, event.action.clone());
+            next_input += 1;
+        }
+
+        // Run until the next dump, the next player action or until the end.
+        let mut stop_tick = end_tick;
+        if let Some(next_dump) = next_dump {
+            stop_tick = stop_tick.min(next_dump);
+        }
+        if let Some(event) = input.get(next_input) {
+            stop_tick = stop_tick.min(event.tick);
+        }
+
+        condition_met = server.tick_until(stop_tick - server.world.tick_counter, |server| {
+            if let Some((name, position)) = &options.until {
+                is_character_at(server, name, *position)
+            } else {
+                false
+            }
+        });
+
+        for error in server.take_errors() {
+            eprintln!("Runtime error: {}", error);
+        }
+
+        if condition_met {
+            break;
+        }
+
+        if next_dump == Some(server.world.tick_counter) {
+            if server.world.tick_counter < end_tick {
+                write_dump(&mut output, &server, &project);
+            }
+            next_dump = options
+                .dump_every
+                .map(|every| server.world.tick_counter + every);
+        }
+    }
+
+    write_dump(&mut output, &server, &project);
+
+    if let Some(path) = &options.snapshot {
+        if std::fs::write(path, server.snapshot().to_json()).is_err() {
+            eprintln!("Unable to write snapshot {}.", path.display());
+        }
+    }
+
+    if let Some(path) = &options.record {
+        let mut log = String::new();
+        for event in server.input_log() {
+            if let Ok(json) = serde_json::to_string(event) {
+                log.push_str(&json);
+                log.push('\n');
+            }
+        }
+        if std::fs::write(path, log).is_err() {
+            eprintln!("Unable to write input log {}.", path.display());
+        }
+    }
+
+    if let Some((name, position)) = &options.until {
+        if condition_met {
+            eprintln!(
+                "Character \"{}\" reached ({}, {}) at tick {}.",
+                name, position.x, position.y, server.world.tick_counter
+            );
+        } else {
+            eprintln!(
+                "Character \"{}\" did not reach ({}, {}) within {} ticks.",
+                name, position.x, position.y, options.ticks
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns true if a character with the given name is located at the given position.
+fn is_character_at(server: &Server, name: &str, position: Vec2i) -> bool {
+    for update in server.get_region_updates().values() {
+        for character in update.characters.values() {
+            if character.name == name
+                && vec2i(character.position.x as i32, character.position.y as i32) == position
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Writes the current region updates as a single JSON line.
+fn write_dump(output: &mut dyn Write, server: &Server, project: &Project) {
+    let mut regions = vec![];
+    for region in &project.regions {
+        if let Some(update) = server.get_region_update(&region.id) {
+            regions.push(RegionDump {
+                id: region.id,
+                name: region.name.clone(),
+                update,
+            });
+        }
+    }
+
+    let dump = TickDump {
+        tick: server.world.tick_counter,
+        regions,
+    };
+
+    if let Ok(json) = serde_json::to_string(&dump) {
+        _ = writeln!(output, "{}", json);
+    }
+}
+
+/// Parses the command line arguments.
+fn parse_options(args: Vec<String>) -> Result<Options, String> {
+    let mut project_path = None;
+    let mut ticks = 100;
+    let mut seed = None;
+    let mut until = None;
+    let mut dump_every = None;
+    let mut output = None;
+    let mut restore = None;
+    let mut snapshot = None;
+    let mut input = None;
+    let mut record = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ticks" => {
+                ticks = parse_number(args.next(), "--ticks")?;
+                if ticks < 0 {
+                    return Err("--ticks expects a number of 0 or more.".to_string());
+                }
+            }
+            "--seed" => {
+                seed = Some(
+                    args.next()
+                        .and_then(|a| a.parse::<u64>().ok())
+                        .ok_or_else(|| "--seed expects a number.".to_string())?,
+                );
+            }
+            "--until" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| "--until expects a character name.".to_string())?;
+                let x = parse_number(args.next(), "--until")?;
+                let y = parse_number(args.next(), "--until")?;
+                until = Some((name, vec2i(x as i32, y as i32)));
+            }
+            "--dump-every" => {
+                let every = parse_number(args.next(), "--dump-every")?;
+                if every <= 0 {
+                    return Err("--dump-every expects a positive number.".to_string());
+                }
+                dump_every = Some(every);
+            }
+            "--output" => {
+                output = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| "--output expects a file name.".to_string())?,
+                ));
+            }
+            "--restore" => {
+                restore = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| "--restore expects a file name.".to_string())?,
+                ));
+            }
+            "--snapshot" => {
+                snapshot = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| "--snapshot expects a file name.".to_string())?,
+                ));
+            }
+            "--input" => {
+                input = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| "--input expects a file name.".to_string())?,
+                ));
+            }
+            "--record" => {
+                record = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| "--record expects a file name.".to_string())?,
+                ));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => {
+                if arg.starts_with("--") || project_path.is_some() {
+                    return Err(format!("Unknown argument: {}", arg));
+                }
+                project_path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+
+    Ok(Options {
+        project_path: project_path.ok_or_else(|| "No project file given.".to_string())?,
+        ticks,
+        seed,
+        until,
+        dump_every,
+        output,
+        restore,
+        snapshot,
+        input,
+        record,
+    })
+}
+
+/// Parses the given argument as a number.
+fn parse_number(arg: Option<String>, option: &str) -> Result<i64, String> {
+    arg.and_then(|a| a.parse::<i64>().ok())
+        .ok_or_else(|| format!("{} expects a number.", option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|a| a.to_string()).collect())
+    }
+
+    #[test]
+    fn defaults() {
+        let options = parse(&["game.eldiron"]).unwrap();
+
+        assert_eq!(options.project_path, PathBuf::from("game.eldiron"));
+        assert_eq!(options.ticks, 100);
+        assert_eq!(options.seed, None);
+        assert!(options.until.is_none());
+        assert_eq!(options.dump_every, None);
+        assert!(options.output.is_none() && options.restore.is_none());
+        assert!(options.snapshot.is_none() && options.input.is_none());
+        assert!(options.record.is_none());
+    }
+
+    #[test]
+    fn all_options() {
+        let options = parse(&[
+            "--ticks",
+            "500",
+            "--seed",
+            "42",
+            "game.eldiron",
+            "--until",
+            "Hero",
+            "3",
+            "7",
+            "--dump-every",
+            "10",
+            "--output",
+            "dump.jsonl",
+            "--restore",
+            "in.json",
+            "--snapshot",
+            "out.json",
+            "--input",
+            "input.jsonl",
+            "--record",
+            "record.jsonl",
+        ])
+        .unwrap();
+
+        assert_eq!(options.project_path, PathBuf::from("game.eldiron"));
+        assert_eq!(options.ticks, 500);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.until, Some(("Hero".to_string(), vec2i(3, 7))));
+        assert_eq!(options.dump_every, Some(10));
+        assert_eq!(options.output, Some(PathBuf::from("dump.jsonl")));
+        assert_eq!(options.restore, Some(PathBuf::from("in.json")));
+        assert_eq!(options.snapshot, Some(PathBuf::from("out.json")));
+        assert_eq!(options.input, Some(PathBuf::from("input.jsonl")));
+        assert_eq!(options.record, Some(PathBuf::from("record.jsonl")));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let error = |args: &[&str]| parse(args).err();
+
+        assert_eq!(error(&[]), Some("No project file given.".to_string()));
+        assert_eq!(
+            error(&["a.eldiron", "b.eldiron"]),
+            Some("Unknown argument: b.eldiron".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--fast"]),
+            Some("Unknown argument: --fast".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--ticks", "many"]),
+            Some("--ticks expects a number.".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--ticks", "-5"]),
+            Some("--ticks expects a number of 0 or more.".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--seed", "-1"]),
+            Some("--seed expects a number.".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--until", "Hero", "3"]),
+            Some("--until expects a number.".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--dump-every", "0"]),
+            Some("--dump-every expects a positive number.".to_string())
+        );
+        assert_eq!(
+            error(&["a.eldiron", "--output"]),
+            Some("--output expects a file name.".to_string())
+        );
+    }
+}
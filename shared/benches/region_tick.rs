@@ -0,0 +1,174 @@
+//! Compares the scheduling of region ticks on the persistent rayon worker threads (as used by
+//! `Server::tick`) with spawning a new thread per region on every tick.
+//!
+//! Usage: cargo bench -p shared --bench region_tick -- [project.eldiron] [--regions n] [--ticks n]
+//!
+//! The regions (and their characters) of the given project are repeated until the requested
+//! number of regions is reached. Without a project empty regions are used, which measures the
+//! pure scheduling overhead.
+
+use rayon::prelude::*;
+use shared::prelude::*;
+use shared::server::functions;
+use shared::server::region_instance::RegionInstance;
+use shared::server::world::World;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use theframework::prelude::*;
+
+fn main() {
+    let mut project = Project::new();
+    let mut regions = 64;
+    let mut ticks = 200;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--regions" => {
+                regions = args.next().and_then(|a| a.parse().ok()).unwrap_or(regions);
+            }
+            "--ticks" => {
+                ticks = args.next().and_then(|a| a.parse().ok()).unwrap_or(ticks);
+            }
+            // Passed by cargo bench.
+            "--bench" => {}
+            _ => {
+                if let Some(p) = std::fs::read_to_string(&arg)
+                    .ok()
+                    .and_then(|contents| serde_json::from_str(&contents).ok())
+                {
+                    project = p;
+                } else {
+                    eprintln!("Unable to read project {}.", arg);
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+
+    println!("Ticking {} regions for {} ticks.", regions, ticks);
+
+    let mut instances = create_instances(&project, regions);
+    let elapsed = run_threads(&mut instances, ticks);
+    print_result("Thread per region", elapsed, ticks);
+
+    let mut instances = create_instances(&project, regions);
+    let elapsed = run_rayon(&mut instances, ticks);
+    print_result("Rayon worker pool", elapsed, ticks);
+}
+
+/// Ticks the instances by spawning a thread per region on every tick.
+fn run_threads(instances: &mut FxHashMap<Uuid, RegionInstance>, ticks: usize) -> Duration {
+    let start = Instant::now();
+    let mut world = World::new();
+
+    for _ in 0..ticks {
+        world.tick();
+        let (sender, receiver) = mpsc::channel();
+        let mut join_handles = vec![];
+
+        for (key, mut instance) in instances.drain() {
+            let sender = sender.clone();
+            let world = world.clone();
+            let handle = std::thread::spawn(move || {
+                instance.tick(&world);
+                sender.send((key, instance)).unwrap();
+            });
+
+            join_handles.push(handle);
+        }
+
+        for handle in join_handles {
+            handle.join().unwrap();
+        }
+
+        drop(sender);
+        for (key, instance) in receiver {
+            instances.insert(key, instance);
+        }
+    }
+
+    start.elapsed()
+}
+
+/// Ticks the instances on the rayon worker threads.
+fn run_rayon(instances: &mut FxHashMap<Uuid, RegionInstance>, ticks: usize) -> Duration {
+    let start = Instant::now();
+    let mut world = World::new();
+
+    for _ in 0..ticks {
+        world.tick();
+        instances
+            .par_iter_mut()
+            .for_each(|(_, instance)| instance.tick(&world));
+    }
+
+    start.elapsed()
+}
+
+/// Creates the given number of region instances by repeating the regions of the project.
+fn create_instances(project: &Project, count: usize) -> FxHashMap<Uuid, RegionInstance> {
+    let tiles = Arc::new(project.extract_tiles());
+    let tile_flags = Arc::new(project.extract_tile_flags());
+
+    let mut compiler = TheCompiler::new();
+    functions::add_compiler_functions(&mut compiler);
+
+    let mut packages = vec![];
+    for bundle in project.characters.values() {
+        let mut bundle = bundle.clone();
+        let mut package = TheCodePackage::new();
+        package.id = bundle.id;
+
+        for grid in bundle.grids.values_mut() {
+            if let Ok(mut module) = compiler.compile(grid) {
+                module.name = grid.name.clone();
+                package.insert_module(module.name.clone(), module);
+            }
+        }
+        packages.push(package);
+    }
+
+    let mut instances = FxHashMap::default();
+
+    for i in 0..count {
+        let mut region = if project.regions.is_empty() {
+            Region::new()
+        } else {
+            project.regions[i % project.regions.len()].clone()
+        };
+        region.id = Uuid::new_v4();
+
+        let mut instance = RegionInstance::new();
+        instance.setup(
+            region.clone(),
+            tiles.clone(),
+            tile_flags.clone(),
+            project,
+            i as u64,
+        );
+
+        for package in &packages {
+            instance.insert_character(package.clone());
+        }
+
+        for character in region.characters.values() {
+            let mut character = character.clone();
+            character.id = Uuid::new_v4();
+            instance.add_character_instance(character);
+        }
+
+        instances.insert(region.id, instance);
+    }
+
+    instances
+}
+
+fn print_result(name: &str, elapsed: Duration, ticks: usize) {
+    println!(
+        "{:<20} {:>10.3} ms total {:>10.3} ms per tick",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        elapsed.as_secs_f64() * 1000.0 / ticks as f64
+    );
+}
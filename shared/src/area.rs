@@ -0,0 +1,33 @@
+use theframework::prelude::*;
+
+/// A named set of tiles in a region. Characters entering, leaving or standing inside of an
+/// area execute their on_enter_area, on_leave_area and on_inside_area functions.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Area {
+    pub id: Uuid,
+    pub name: String,
+
+    pub area: FxHashSet<(i32, i32)>,
+}
+
+impl Default for Area {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Area {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "New Area".to_string(),
+
+            area: FxHashSet::default(),
+        }
+    }
+
+    /// Returns true if the area contains the given position.
+    pub fn contains(&self, pos: Vec2i) -> bool {
+        self.area.contains(&(pos.x, pos.y))
+    }
+}
@@ -27,4 +27,101 @@ impl Character {
             instance: TheCodeBundle::new(),
         }
     }
+
+    /// Returns the position set in the init grid of the instance.
+    pub fn position(&self) -> Option<Vec2i> {
+        bundle_position(&self.instance)
+    }
+
+    /// Sets the position in the init grid of the instance. Returns false if the instance does
+    /// not set a position.
+    pub fn set_position(&mut self, pos: Vec2i) -> bool {
+        set_bundle_position(&mut self.instance, pos)
+    }
+}
+
+/// Returns the position assigned to "self" in the init grid of the instance bundle.
+pub fn bundle_position(bundle: &TheCodeBundle) -> Option<Vec2i> {
+    for grid in bundle.grids.values() {
+        if grid.name == "init" {
+            for ((x, y), atom) in &grid.code {
+                if let TheCodeAtom::ObjectSet(object, property) = atom {
+                    if object == "self" && property == "position" {
+                        if let Some(TheCodeAtom::Value(TheValue::Position(p))) =
+                            grid.code.get(&(x + 2, *y))
+                        {
+                            return Some(vec2i(p.x as i32, p.y as i32));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Sets the position assigned to "self" in the init grid of the instance bundle.
+pub fn set_bundle_position(bundle: &mut TheCodeBundle, pos: Vec2i) -> bool {
+    for grid in bundle.grids.values_mut() {
+        if grid.name == "init" {
+            let mut location = None;
+            for ((x, y), atom) in &grid.code {
+                if let TheCodeAtom::ObjectSet(object, property) = atom {
+                    if object == "self" && property == "position" {
+                        location = Some((x + 2, *y));
+                    }
+                }
+            }
+            if let Some(location) = location {
+                if let Some(TheCodeAtom::Value(TheValue::Position(p))) =
+                    grid.code.get_mut(&location)
+                {
+                    *p = vec3f(pos.x as f32, pos.y as f32, p.z);
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Returns a character instance with the given position in the init grid of its instance
+    /// bundle, like the instances placed by the region editor.
+    pub(crate) fn character_at(x: i32, y: i32) -> Character {
+        let mut init = TheCodeGrid {
+            name: "init".into(),
+            ..Default::default()
+        };
+        init.insert_atom(
+            (0, 0),
+            TheCodeAtom::ObjectSet("self".to_string(), "position".to_string()),
+        );
+        init.insert_atom((1, 0), TheCodeAtom::Assignment("=".to_string()));
+        init.insert_atom(
+            (2, 0),
+            TheCodeAtom::Value(TheValue::Position(vec3f(x as f32, y as f32, 0.0))),
+        );
+
+        let mut character = Character::new();
+        character.instance.insert_grid(init);
+        character.id = character.instance.id;
+        character
+    }
+
+    #[test]
+    fn position_of_the_init_grid() {
+        let mut character = character_at(3, 4);
+        assert_eq!(character.position(), Some(vec2i(3, 4)));
+
+        assert!(character.set_position(vec2i(5, 1)));
+        assert_eq!(character.position(), Some(vec2i(5, 1)));
+
+        let mut empty = Character::new();
+        assert_eq!(empty.position(), None);
+        assert!(!empty.set_position(vec2i(5, 1)));
+    }
 }
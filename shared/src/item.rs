@@ -0,0 +1,42 @@
+use crate::character::{bundle_position, set_bundle_position};
+use theframework::prelude::*;
+
+/// An item instance.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Item {
+    pub id: Uuid,
+
+    /// The id of the item bundle.
+    pub item_id: Uuid,
+
+    /// The custom bundle to override the default behavior.
+    pub instance: TheCodeBundle,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Item {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            item_id: Uuid::new_v4(),
+
+            instance: TheCodeBundle::new(),
+        }
+    }
+
+    /// Returns the position set in the init grid of the instance.
+    pub fn position(&self) -> Option<Vec2i> {
+        bundle_position(&self.instance)
+    }
+
+    /// Sets the position in the init grid of the instance. Returns false if the instance does
+    /// not set a position.
+    pub fn set_position(&mut self, pos: Vec2i) -> bool {
+        set_bundle_position(&mut self.instance, pos)
+    }
+}
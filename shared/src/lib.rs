@@ -1,24 +1,39 @@
+pub mod area;
 pub mod camera;
 pub mod character;
+pub mod item;
+pub mod lighting;
+pub mod prefab;
 pub mod project;
 pub mod region;
 pub mod renderer;
 pub mod server;
 pub mod tiledrawer;
 pub mod tilemap;
+pub mod undo;
 
 pub mod prelude {
     pub use ::serde::{Deserialize, Serialize};
 
+    pub use crate::area::Area;
     pub use crate::camera::{Camera, Ray};
     pub use crate::character::Character;
+    pub use crate::item::Item;
+    pub use crate::lighting::{Light, LightMap};
+    pub use crate::prefab::Prefab;
     pub use crate::project::Project;
-    pub use crate::region::{Layer2DRole, Region, RegionTile};
+    pub use crate::region::{Layer2DRole, Region, RegionAnchor, RegionTile};
     pub use crate::renderer::Renderer;
+    pub use crate::server::action::{InputEvent, PlayerAction, PlayerDirection};
     pub use crate::server::context::ServerContext;
-    pub use crate::server::update::{CharacterUpdate, RegionUpdate};
+    pub use crate::server::diagnostics::CompileDiagnostic;
+    pub use crate::server::inventory::Inventory;
+    pub use crate::server::snapshot::{RegionSnapshot, ServerSnapshot};
+    pub use crate::server::update::{CharacterEffect, CharacterUpdate, ItemUpdate, RegionUpdate};
+    pub use crate::server::world::{DayPhase, World, WorldDate};
     pub use crate::server::{Server, ServerState};
     pub use crate::tiledrawer::TileDrawer;
-    pub use crate::tilemap::{Tile, TileRole, Tilemap};
+    pub use crate::tilemap::{Autotile, AutotileBits, Tile, TileFlags, TileRole, Tilemap};
+    pub use crate::undo::RegionUndo;
     pub use rand::prelude::*;
 }
@@ -0,0 +1,133 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+/// The ambient light at night, the darkest a region gets without light sources.
+pub const MIN_AMBIENT: f32 = 0.3;
+
+/// Returns the ambient light for the given daylight (0.0 at night, 1.0 during the day).
+pub fn ambient_light(daylight: f32) -> f32 {
+    MIN_AMBIENT + (1.0 - MIN_AMBIENT) * daylight.clamp(0.0, 1.0)
+}
+
+/// A point light source in a region.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub struct Light {
+    pub position: Vec2i,
+
+    /// The radius of the light in tiles.
+    pub radius: i32,
+}
+
+impl Light {
+    pub fn new(position: Vec2i, radius: i32) -> Self {
+        Self { position, radius }
+    }
+}
+
+/// The brightness of the tiles of a region.
+#[derive(PartialEq, Clone, Debug)]
+pub struct LightMap {
+    /// The ambient light of all tiles.
+    pub ambient: f32,
+
+    /// The light added by the light sources to each lit tile.
+    pub levels: FxHashMap<(i32, i32), f32>,
+}
+
+impl Default for LightMap {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl LightMap {
+    pub fn new(ambient: f32) -> Self {
+        Self {
+            ambient,
+            levels: FxHashMap::default(),
+        }
+    }
+
+    /// Computes the light map of the region. The light of each source fades out linearly
+    /// towards its radius and does not pass vision-blocking tiles.
+    pub fn compute(
+        region: &Region,
+        tiles: &FxHashMap<Uuid, TheRGBATile>,
+        tile_flags: &FxHashMap<Uuid, TileFlags>,
+        lights: &[Light],
+        ambient: f32,
+    ) -> Self {
+        let mut light_map = Self::new(ambient);
+
+        for light in lights {
+            if light.radius <= 0 {
+                continue;
+            }
+
+            let lit = region.field_of_view(light.position, light.radius, |pos| {
+                region.blocks_vision(pos, tiles, tile_flags)
+            });
+
+            for (x, y) in lit {
+                let dx = (x - light.position.x) as f32;
+                let dy = (y - light.position.y) as f32;
+                let distance = (dx * dx + dy * dy).sqrt();
+
+                let level = 1.0 - distance / (light.radius + 1) as f32;
+                if level > 0.0 {
+                    *light_map.levels.entry((x, y)).or_default() += level;
+                }
+            }
+        }
+
+        light_map
+    }
+
+    /// Returns true if the light map leaves all tiles at full brightness.
+    pub fn is_full_bright(&self) -> bool {
+        self.ambient >= 1.0
+    }
+
+    /// Returns the brightness of the given tile.
+    pub fn brightness(&self, x: i32, y: i32) -> f32 {
+        (self.ambient + self.levels.get(&(x, y)).copied().unwrap_or(0.0)).clamp(0.0, 1.0)
+    }
+
+    /// Returns the brightness at the given position in tile units, interpolated between the
+    /// centers of the surrounding tiles.
+    pub fn brightness_at(&self, pos: Vec2f) -> f32 {
+        let x = pos.x - 0.5;
+        let y = pos.y - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.brightness(x0, y0) * (1.0 - fx) + self.brightness(x0 + 1, y0) * fx;
+        let bottom =
+            self.brightness(x0, y0 + 1) * (1.0 - fx) + self.brightness(x0 + 1, y0 + 1) * fx;
+
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Returns the lights of the light emitting tiles of the region, sorted by position.
+pub fn tile_lights(region: &Region, tile_flags: &FxHashMap<Uuid, TileFlags>) -> Vec<Light> {
+    let mut lights: Vec<Light> = region
+        .tiles
+        .keys()
+        .filter_map(|(x, y)| {
+            let radius = region.flags_at(vec2i(*x, *y), tile_flags).light;
+            if radius > 0 {
+                Some(Light::new(vec2i(*x, *y), radius))
+            } else {
+                None
+            }
+        })
+        .collect();
+    lights.sort_by_key(|light| (light.position.x, light.position.y));
+    lights
+}
@@ -0,0 +1,277 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+/// A reusable part of a region, like a house or a shop. Holds the tiles and optionally the
+/// character instances of a rectangular selection, with positions relative to its top left.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Prefab {
+    pub id: Uuid,
+    pub name: String,
+
+    pub width: i32,
+    pub height: i32,
+
+    #[serde(with = "vectorize")]
+    pub tiles: FxHashMap<(i32, i32), RegionTile>,
+
+    #[serde(default)]
+    pub characters: Vec<Character>,
+}
+
+impl Default for Prefab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "Unnamed".to_string(),
+
+            width: 0,
+            height: 0,
+
+            tiles: FxHashMap::default(),
+            characters: vec![],
+        }
+    }
+
+    /// Copies the tiles inside the bounding rectangle of the selection, and the character
+    /// instances on the selected tiles if characters is true. Returns None if the selection is
+    /// empty.
+    pub fn from_selection(
+        region: &Region,
+        selection: &FxHashSet<(i32, i32)>,
+        characters: bool,
+    ) -> Option<Self> {
+        let min_x = selection.iter().map(|(x, _)| *x).min()?;
+        let min_y = selection.iter().map(|(_, y)| *y).min()?;
+        let max_x = selection.iter().map(|(x, _)| *x).max()?;
+        let max_y = selection.iter().map(|(_, y)| *y).max()?;
+
+        let mut prefab = Self::new();
+        prefab.width = max_x - min_x + 1;
+        prefab.height = max_y - min_y + 1;
+
+        for (x, y) in Region::rect_positions(vec2i(min_x, min_y), vec2i(max_x, max_y), false) {
+            if let Some(tile) = region.tiles.get(&(x, y)) {
+                prefab.tiles.insert((x - min_x, y - min_y), tile.clone());
+            }
+        }
+
+        if characters {
+            let mut ids: Vec<&Uuid> = region.characters.keys().collect();
+            ids.sort();
+            for id in ids {
+                let mut character = region.characters[id].clone();
+                if let Some(pos) = character.position() {
+                    if selection.contains(&(pos.x, pos.y)) {
+                        character.set_position(vec2i(pos.x - min_x, pos.y - min_y));
+                        prefab.characters.push(character);
+                    }
+                }
+            }
+        }
+
+        Some(prefab)
+    }
+
+    /// Returns true if the prefab contains neither tiles nor characters.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty() && self.characters.is_empty()
+    }
+
+    /// Rotates the prefab clockwise by 90 degrees.
+    pub fn rotate(&mut self) {
+        let height = self.height;
+        self.transform(|pos| vec2i(height - 1 - pos.y, pos.x));
+        std::mem::swap(&mut self.width, &mut self.height);
+    }
+
+    /// Mirrors the prefab horizontally.
+    pub fn flip_horizontally(&mut self) {
+        let width = self.width;
+        self.transform(|pos| vec2i(width - 1 - pos.x, pos.y));
+    }
+
+    /// Mirrors the prefab vertically.
+    pub fn flip_vertically(&mut self) {
+        let height = self.height;
+        self.transform(|pos| vec2i(pos.x, height - 1 - pos.y));
+    }
+
+    /// Moves the tiles and characters to their transformed positions.
+    fn transform<F: Fn(Vec2i) -> Vec2i>(&mut self, f: F) {
+        self.tiles = self
+            .tiles
+            .drain()
+            .map(|((x, y), tile)| {
+                let pos = f(vec2i(x, y));
+                ((pos.x, pos.y), tile)
+            })
+            .collect();
+
+        for character in &mut self.characters {
+            if let Some(pos) = character.position() {
+                character.set_position(f(pos));
+            }
+        }
+    }
+
+    /// Pastes the prefab into the region with its top left at the given position. Tiles and
+    /// characters outside of the region are skipped, the characters are added as new instances.
+    /// Returns the changes as one undo step.
+    pub fn stamp(&self, region: &mut Region, at: Vec2i) -> RegionUndo {
+        let mut region_undo = RegionUndo::new(region.id);
+
+        for ((x, y), tile) in &self.tiles {
+            let pos = (at.x + x, at.y + y);
+            if region.contains(vec2i(pos.0, pos.1)) {
+                let before = region.tiles.insert(pos, tile.clone());
+                region_undo.record_tile(pos, before, Some(tile.clone()));
+            }
+        }
+
+        for character in &self.characters {
+            if let Some(pos) = character.position() {
+                let pos = vec2i(at.x + pos.x, at.y + pos.y);
+                if region.contains(pos) {
+                    let mut character = character.clone();
+                    character.instance.id = Uuid::new_v4();
+                    character.id = character.instance.id;
+                    character.set_position(pos);
+
+                    region.characters.insert(character.id, character.clone());
+                    region_undo.record_character(character.id, None, Some(character));
+                }
+            }
+        }
+
+        region_undo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::tests::character_at;
+
+    /// Returns a 3x2 prefab with tiles in the top left and bottom right corners and a
+    /// character in the top right corner.
+    fn prefab() -> Prefab {
+        let mut prefab = Prefab::new();
+        prefab.width = 3;
+        prefab.height = 2;
+
+        let mut tile = RegionTile::new();
+        tile.layers[0] = Some(Uuid::new_v4());
+        prefab.tiles.insert((0, 0), tile.clone());
+        prefab.tiles.insert((2, 1), tile);
+        prefab.characters.push(character_at(2, 0));
+        prefab
+    }
+
+    /// Returns the sorted tile positions and the character positions of the prefab.
+    fn positions(prefab: &Prefab) -> (Vec<(i32, i32)>, Vec<Option<Vec2i>>) {
+        let mut tiles: Vec<(i32, i32)> = prefab.tiles.keys().copied().collect();
+        tiles.sort();
+        let characters = prefab.characters.iter().map(|c| c.position()).collect();
+        (tiles, characters)
+    }
+
+    #[test]
+    fn from_selection_copies_the_bounding_rectangle() {
+        let mut region = Region::new();
+        region.set_tile((2, 2), Layer2DRole::Ground, Some(Uuid::new_v4()));
+        region.set_tile((3, 4), Layer2DRole::Wall, Some(Uuid::new_v4()));
+        region.set_tile((9, 9), Layer2DRole::Ground, Some(Uuid::new_v4()));
+        let inside = character_at(3, 4);
+        let outside = character_at(2, 4);
+        region.characters.insert(inside.id, inside.clone());
+        region.characters.insert(outside.id, outside);
+
+        let selection: FxHashSet<(i32, i32)> = [(2, 2), (3, 4)].into_iter().collect();
+
+        let prefab = Prefab::from_selection(&region, &selection, false).unwrap();
+        assert_eq!((prefab.width, prefab.height), (2, 3));
+        assert_eq!(positions(&prefab), (vec![(0, 0), (1, 2)], vec![]));
+        assert_eq!(prefab.tiles[&(1, 2)], region.tiles[&(3, 4)]);
+
+        let prefab = Prefab::from_selection(&region, &selection, true).unwrap();
+        assert_eq!(prefab.characters.len(), 1);
+        assert_eq!(prefab.characters[0].id, inside.id);
+        assert_eq!(prefab.characters[0].position(), Some(vec2i(1, 2)));
+
+        assert!(Prefab::from_selection(&region, &FxHashSet::default(), true).is_none());
+    }
+
+    #[test]
+    fn rotate_clockwise() {
+        let mut prefab = prefab();
+        let original = prefab.clone();
+
+        prefab.rotate();
+        assert_eq!((prefab.width, prefab.height), (2, 3));
+        assert_eq!(
+            positions(&prefab),
+            (vec![(0, 2), (1, 0)], vec![Some(vec2i(1, 2))])
+        );
+
+        for _ in 0..3 {
+            prefab.rotate();
+        }
+        assert_eq!(prefab, original);
+    }
+
+    #[test]
+    fn flip_horizontally_and_vertically() {
+        let mut prefab = prefab();
+        let original = prefab.clone();
+
+        prefab.flip_horizontally();
+        assert_eq!((prefab.width, prefab.height), (3, 2));
+        assert_eq!(
+            positions(&prefab),
+            (vec![(0, 1), (2, 0)], vec![Some(vec2i(0, 0))])
+        );
+        prefab.flip_horizontally();
+        assert_eq!(prefab, original);
+
+        prefab.flip_vertically();
+        assert_eq!(
+            positions(&prefab),
+            (vec![(0, 1), (2, 0)], vec![Some(vec2i(2, 1))])
+        );
+    }
+
+    #[test]
+    fn stamp_adds_new_instances_inside_the_region() {
+        let prefab = prefab();
+        let mut region = Region::new();
+        region.width = 4;
+        region.height = 4;
+        let original = region.clone();
+
+        let region_undo = prefab.stamp(&mut region, vec2i(1, 3));
+
+        let mut tiles: Vec<(i32, i32)> = region.tiles.keys().copied().collect();
+        tiles.sort();
+        assert_eq!(tiles, vec![(1, 3)]);
+        assert_eq!(region.characters.len(), 1);
+
+        let character = region.characters.values().next().unwrap();
+        assert_ne!(character.id, prefab.characters[0].id);
+        assert_eq!(character.id, character.instance.id);
+        assert_eq!(character.position(), Some(vec2i(3, 3)));
+
+        region_undo.inverted().apply(&mut region);
+        assert_eq!(region, original);
+
+        // Stamping twice creates two instances.
+        prefab.stamp(&mut region, vec2i(0, 0));
+        prefab.stamp(&mut region, vec2i(0, 2));
+        assert_eq!(region.characters.len(), 2);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::server::world::default_ticks_per_minute;
 use theframework::prelude::*;
 
 /// The default target fps for the game.
@@ -23,12 +24,17 @@ pub struct Project {
     pub items: FxHashMap<Uuid, TheCodeBundle>,
     #[serde(default)]
     pub codes: FxHashMap<Uuid, TheCodeBundle>,
+    #[serde(default)]
+    pub prefabs: FxHashMap<Uuid, Prefab>,
 
     #[serde(default = "default_target_fps")]
     pub target_fps: u32,
 
     #[serde(default = "default_tick_ms")]
     pub tick_ms: u32,
+
+    #[serde(default = "default_ticks_per_minute")]
+    pub ticks_per_minute: i64,
 }
 
 impl Default for Project {
@@ -48,9 +54,11 @@ impl Project {
             characters: FxHashMap::default(),
             items: FxHashMap::default(),
             codes: FxHashMap::default(),
+            prefabs: FxHashMap::default(),
 
             target_fps: default_target_fps(),
             tick_ms: default_tick_ms(),
+            ticks_per_minute: default_ticks_per_minute(),
         }
     }
 
@@ -76,6 +84,34 @@ impl Project {
         entries
     }
 
+    /// Adds the prefab to the project, replacing a prefab with the same name.
+    pub fn add_prefab(&mut self, prefab: Prefab) {
+        self.prefabs.retain(|_, p| p.name != prefab.name);
+        self.prefabs.insert(prefab.id, prefab);
+    }
+
+    /// Returns the prefab with the given name.
+    pub fn get_prefab_by_name(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.values().find(|prefab| prefab.name == name)
+    }
+
+    /// Removes the given prefab from the project.
+    pub fn remove_prefab(&mut self, id: &Uuid) {
+        self.prefabs.remove(id);
+    }
+
+    /// Returns a list of all prefabs sorted by name.
+    pub fn sorted_prefab_list(&self) -> Vec<(Uuid, String)> {
+        let mut entries: Vec<(Uuid, String)> = self
+            .prefabs
+            .iter()
+            .map(|(uuid, prefab)| (*uuid, prefab.name.clone()))
+            .collect();
+
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+        entries
+    }
+
     /// Add Item
     pub fn add_item(&mut self, item: TheCodeBundle) {
         self.items.insert(item.id, item);
@@ -150,6 +186,26 @@ impl Project {
         tiles
     }
 
+    /// Extract the flags of all tiles from all tilemaps and store them in a hash.
+    pub fn extract_tile_flags(&self) -> FxHashMap<Uuid, TileFlags> {
+        let mut flags = FxHashMap::default();
+        for tilemap in &self.tilemaps {
+            for tile in &tilemap.tiles {
+                flags.insert(tile.id, tile.flags);
+            }
+        }
+        flags
+    }
+
+    /// Extract the autotile rule sets of all tilemaps.
+    pub fn extract_autotiles(&self) -> Vec<Autotile> {
+        let mut autotiles = vec![];
+        for tilemap in &self.tilemaps {
+            autotiles.extend(tilemap.autotiles.iter().cloned());
+        }
+        autotiles
+    }
+
     /// Extract all tiles from all tilemaps and store them in a vec.
     pub fn extract_tiles_vec(&self) -> Vec<TheRGBATile> {
         let mut tiles = vec![];
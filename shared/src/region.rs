@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use pathfinding::prelude::astar;
 use theframework::prelude::*;
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
@@ -19,6 +20,12 @@ pub struct Region {
     #[serde(default)]
     pub characters: FxHashMap<Uuid, Character>,
 
+    #[serde(default)]
+    pub areas: FxHashMap<Uuid, Area>,
+
+    #[serde(default)]
+    pub items: FxHashMap<Uuid, Item>,
+
     pub width: i32,
     pub height: i32,
     pub grid_size: i32,
@@ -42,6 +49,8 @@ impl Region {
             tiles: FxHashMap::default(),
 
             characters: FxHashMap::default(),
+            areas: FxHashMap::default(),
+            items: FxHashMap::default(),
 
             width: 80,
             height: 80,
@@ -88,6 +97,438 @@ impl Region {
         can_move
     }
 
+    /// Returns the ids of all areas containing the given position, sorted by id.
+    pub fn areas_at(&self, pos: Vec2i) -> Vec<Uuid> {
+        let mut areas: Vec<Uuid> = self
+            .areas
+            .values()
+            .filter(|area| area.contains(pos))
+            .map(|area| area.id)
+            .collect();
+        areas.sort();
+        areas
+    }
+
+    /// Finds the cheapest path between the two positions using A*. The movement_cost closure
+    /// returns the cost of entering a position, or None if it is not passable. The destination
+    /// itself does not need to be passable, so that characters can close in on other
+    /// characters.
+    pub fn find_path<F>(&self, from: Vec2i, to: Vec2i, movement_cost: F) -> Option<Vec<Vec2i>>
+    where
+        F: Fn(Vec2i) -> Option<i32>,
+    {
+        let goal = (to.x, to.y);
+        let result = astar(
+            &(from.x, from.y),
+            |&(x, y)| {
+                [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                    .into_iter()
+                    .filter_map(|(x, y)| {
+                        if (x, y) == goal {
+                            if x >= 0 && y >= 0 && x < self.width && y < self.height {
+                                Some(((x, y), 1))
+                            } else {
+                                None
+                            }
+                        } else {
+                            movement_cost(vec2i(x, y)).map(|cost| ((x, y), cost.max(1)))
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |&(x, y)| (x - goal.0).abs() + (y - goal.1).abs(),
+            |&p| p == goal,
+        );
+
+        result.map(|(path, _)| path.into_iter().map(|(x, y)| vec2i(x, y)).collect())
+    }
+
+    /// Returns the combined flags of all tile layers at the given position.
+    pub fn flags_at(&self, pos: Vec2i, tile_flags: &FxHashMap<Uuid, TileFlags>) -> TileFlags {
+        let mut flags = TileFlags::default();
+        if let Some(tile) = self.tiles.get(&(pos.x, pos.y)) {
+            for layer in tile.layers.iter().flatten() {
+                if let Some(f) = tile_flags.get(layer) {
+                    flags = flags.combine(f);
+                }
+            }
+        }
+        flags
+    }
+
+    /// Returns true if the tile at the given position blocks the view. Positions outside of
+    /// the region block the view. Blocking wall tiles without any flags set (like the tiles of
+    /// projects created before tile flags existed) block the view too.
+    pub fn blocks_vision(
+        &self,
+        pos: Vec2i,
+        tiles: &FxHashMap<Uuid, TheRGBATile>,
+        tile_flags: &FxHashMap<Uuid, TileFlags>,
+    ) -> bool {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.width || pos.y >= self.height {
+            return true;
+        }
+
+        if self.flags_at(pos, tile_flags).blocks_vision {
+            return true;
+        }
+
+        if let Some(wall) = self.layer_at(pos, Layer2DRole::Wall) {
+            let no_flags = tile_flags
+                .get(&wall)
+                .map_or(true, |flags| *flags == TileFlags::default());
+            if no_flags {
+                return tiles.get(&wall).map_or(false, |tile| tile.blocking);
+            }
+        }
+
+        false
+    }
+
+    /// Returns true if there is a free line of sight between the two positions. The tiles
+    /// in between are traced with Bresenham's algorithm, the two end points themselves may
+    /// block the view.
+    pub fn line_of_sight<F>(&self, from: Vec2i, to: Vec2i, blocks_vision: F) -> bool
+    where
+        F: Fn(Vec2i) -> bool,
+    {
+        let dx = (to.x - from.x).abs();
+        let dy = -(to.y - from.y).abs();
+        let sx = if from.x < to.x { 1 } else { -1 };
+        let sy = if from.y < to.y { 1 } else { -1 };
+
+        let mut err = dx + dy;
+        let mut x = from.x;
+        let mut y = from.y;
+
+        loop {
+            if (x, y) == (to.x, to.y) {
+                return true;
+            }
+            if (x, y) != (from.x, from.y) && blocks_vision(vec2i(x, y)) {
+                return false;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Computes the field of view from the given position up to the given radius using
+    /// recursive shadowcasting. Returns the visible positions, including the blocking tiles
+    /// which are visible themselves.
+    pub fn field_of_view<F>(
+        &self,
+        origin: Vec2i,
+        radius: i32,
+        blocks_vision: F,
+    ) -> FxHashSet<(i32, i32)>
+    where
+        F: Fn(Vec2i) -> bool,
+    {
+        // The transformations of the eight octants as (xx, xy, yx, yy).
+        const OCTANTS: [(i32, i32, i32, i32); 8] = [
+            (1, 0, 0, 1),
+            (0, 1, 1, 0),
+            (0, -1, 1, 0),
+            (-1, 0, 0, 1),
+            (-1, 0, 0, -1),
+            (0, -1, -1, 0),
+            (0, 1, -1, 0),
+            (1, 0, 0, -1),
+        ];
+
+        let mut caster = ShadowCaster {
+            region: self,
+            origin,
+            radius,
+            transform: OCTANTS[0],
+            blocks_vision: &blocks_vision,
+            visible: FxHashSet::default(),
+        };
+        caster.visible.insert((origin.x, origin.y));
+
+        for transform in OCTANTS {
+            caster.transform = transform;
+            caster.cast(1, 1.0, 0.0);
+        }
+
+        caster.visible
+    }
+
+    /// Returns true if the position is inside the region.
+    pub fn contains(&self, pos: Vec2i) -> bool {
+        pos.x >= 0 && pos.y >= 0 && pos.x < self.width && pos.y < self.height
+    }
+
+    /// Returns the tile of the given layer at the position.
+    pub fn layer_at(&self, pos: Vec2i, role: Layer2DRole) -> Option<Uuid> {
+        self.tiles
+            .get(&(pos.x, pos.y))
+            .and_then(|tile| tile.layers.get(role as usize).copied().flatten())
+    }
+
+    /// Replaces the tiles at the given positions which belong to an autotile rule set with the
+    /// variant matching their neighbors, in all layers.
+    pub fn update_autotiles(&mut self, positions: &[(i32, i32)], autotiles: &[Autotile]) {
+        if autotiles.is_empty() {
+            return;
+        }
+
+        for (x, y) in positions {
+            let layers = if let Some(tile) = self.tiles.get(&(*x, *y)) {
+                tile.layers.clone()
+            } else {
+                continue;
+            };
+
+            for (index, layer) in layers.iter().enumerate() {
+                if let Some(tile_id) = layer {
+                    if let Some(autotile) = autotiles.iter().find(|a| a.contains(tile_id)) {
+                        let mask = autotile.mask(|dx, dy| {
+                            self.tiles
+                                .get(&(x + dx, y + dy))
+                                .and_then(|tile| tile.layers.get(index).copied().flatten())
+                                .is_some_and(|id| autotile.contains(&id))
+                        });
+                        if let Some(variant) = autotile.tile_for_mask(mask) {
+                            if let Some(tile) = self.tiles.get_mut(&(*x, *y)) {
+                                tile.layers[index] = Some(variant);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the given positions together with their neighbors inside the region, the tiles
+    /// whose autotile variant may change by an edit at the positions. Sorted by position.
+    pub fn autotile_neighborhood(&self, positions: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        let mut neighborhood: FxHashSet<(i32, i32)> = FxHashSet::default();
+        for (x, y) in positions {
+            for pos in Self::rect_positions(vec2i(x - 1, y - 1), vec2i(x + 1, y + 1), false) {
+                if self.contains(vec2i(pos.0, pos.1)) {
+                    neighborhood.insert(pos);
+                }
+            }
+        }
+        let mut neighborhood: Vec<(i32, i32)> = neighborhood.into_iter().collect();
+        neighborhood.sort();
+        neighborhood
+    }
+
+    /// Returns the positions of the rectangle spanned by the two corners. If outline is true
+    /// only the border of the rectangle is returned.
+    pub fn rect_positions(from: Vec2i, to: Vec2i, outline: bool) -> Vec<(i32, i32)> {
+        let (min_x, max_x) = (from.x.min(to.x), from.x.max(to.x));
+        let (min_y, max_y) = (from.y.min(to.y), from.y.max(to.y));
+
+        let mut positions = vec![];
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if !outline || x == min_x || x == max_x || y == min_y || y == max_y {
+                    positions.push((x, y));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Returns the positions of the line between the two positions, using Bresenham's
+    /// algorithm.
+    pub fn line_positions(from: Vec2i, to: Vec2i) -> Vec<(i32, i32)> {
+        let dx = (to.x - from.x).abs();
+        let dy = -(to.y - from.y).abs();
+        let sx = if from.x < to.x { 1 } else { -1 };
+        let sy = if from.y < to.y { 1 } else { -1 };
+
+        let mut err = dx + dy;
+        let mut x = from.x;
+        let mut y = from.y;
+
+        let mut positions = vec![(x, y)];
+        while (x, y) != (to.x, to.y) {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            positions.push((x, y));
+        }
+        positions
+    }
+
+    /// Returns the connected positions inside the region which have the same tile in the given
+    /// layer as the start position (4-neighborhood).
+    pub fn flood_fill_positions(&self, start: Vec2i, role: Layer2DRole) -> Vec<(i32, i32)> {
+        if !self.contains(start) {
+            return vec![];
+        }
+
+        let target = self.layer_at(start, role);
+
+        let mut positions = vec![];
+        let mut visited: FxHashSet<(i32, i32)> = FxHashSet::default();
+        let mut stack = vec![(start.x, start.y)];
+        visited.insert((start.x, start.y));
+
+        while let Some((x, y)) = stack.pop() {
+            positions.push((x, y));
+            for (nx, ny) in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                let pos = vec2i(nx, ny);
+                if self.contains(pos)
+                    && !visited.contains(&(nx, ny))
+                    && self.layer_at(pos, role) == target
+                {
+                    visited.insert((nx, ny));
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Resizes the region, keeping the content fixed at the anchor. Content outside of the new
+    /// size is removed. Returns the changes as one undo step.
+    pub fn resize(&mut self, width: i32, height: i32, anchor: RegionAnchor) -> RegionUndo {
+        let width = width.max(1);
+        let height = height.max(1);
+        let offset = anchor.offset((self.width, self.height), (width, height));
+        self.relocate_content(offset, width, height)
+    }
+
+    /// Moves all content of the region by the offset. Content moved outside of the region is
+    /// removed. Returns the changes as one undo step.
+    pub fn shift_content(&mut self, offset: Vec2i) -> RegionUndo {
+        self.relocate_content(offset, self.width, self.height)
+    }
+
+    /// Shrinks the region to the bounding rectangle of its tiles, areas, characters and items.
+    /// Returns the changes as one undo step, which is empty if the region has no content.
+    pub fn crop_to_content(&mut self) -> RegionUndo {
+        let mut positions: Vec<(i32, i32)> = self.tiles.keys().copied().collect();
+        for area in self.areas.values() {
+            positions.extend(area.area.iter().copied());
+        }
+        for character in self.characters.values() {
+            if let Some(pos) = character.position() {
+                positions.push((pos.x, pos.y));
+            }
+        }
+        for item in self.items.values() {
+            if let Some(pos) = item.position() {
+                positions.push((pos.x, pos.y));
+            }
+        }
+        positions.retain(|(x, y)| self.contains(vec2i(*x, *y)));
+
+        if positions.is_empty() {
+            return RegionUndo::new(self.id);
+        }
+
+        let min_x = positions.iter().map(|(x, _)| *x).min().unwrap_or(0);
+        let min_y = positions.iter().map(|(_, y)| *y).min().unwrap_or(0);
+        let max_x = positions.iter().map(|(x, _)| *x).max().unwrap_or(0);
+        let max_y = positions.iter().map(|(_, y)| *y).max().unwrap_or(0);
+
+        self.relocate_content(vec2i(-min_x, -min_y), max_x - min_x + 1, max_y - min_y + 1)
+    }
+
+    /// Moves all content by the offset and sets the new size, removing content outside of it.
+    fn relocate_content(&mut self, offset: Vec2i, width: i32, height: i32) -> RegionUndo {
+        let mut region_undo = RegionUndo::new(self.id);
+        let inside = |(x, y): (i32, i32)| x >= 0 && y >= 0 && x < width && y < height;
+        let moved = |(x, y): (i32, i32)| (x + offset.x, y + offset.y);
+
+        // Tiles
+        let tiles = std::mem::take(&mut self.tiles);
+        for (pos, tile) in &tiles {
+            let pos = moved(*pos);
+            if inside(pos) {
+                self.tiles.insert(pos, tile.clone());
+            }
+        }
+        let mut positions: Vec<(i32, i32)> =
+            tiles.keys().chain(self.tiles.keys()).copied().collect();
+        positions.sort();
+        positions.dedup();
+        for pos in positions {
+            region_undo.record_tile(pos, tiles.get(&pos).cloned(), self.tiles.get(&pos).cloned());
+        }
+
+        // Areas
+        let mut ids: Vec<Uuid> = self.areas.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            if let Some(area) = self.areas.get_mut(&id) {
+                let before = area.clone();
+                area.area = before
+                    .area
+                    .iter()
+                    .map(|pos| moved(*pos))
+                    .filter(|pos| inside(*pos))
+                    .collect();
+                region_undo.record_area(id, Some(before), Some(area.clone()));
+            }
+        }
+
+        // Characters
+        let mut ids: Vec<Uuid> = self.characters.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            if let Some(character) = self.characters.get_mut(&id) {
+                if let Some(pos) = character.position() {
+                    let before = character.clone();
+                    let pos = moved((pos.x, pos.y));
+                    if inside(pos) {
+                        character.set_position(vec2i(pos.0, pos.1));
+                        region_undo.record_character(id, Some(before), Some(character.clone()));
+                    } else {
+                        self.characters.remove(&id);
+                        region_undo.record_character(id, Some(before), None);
+                    }
+                }
+            }
+        }
+
+        // Items
+        let mut ids: Vec<Uuid> = self.items.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            if let Some(item) = self.items.get_mut(&id) {
+                if let Some(pos) = item.position() {
+                    let before = item.clone();
+                    let pos = moved((pos.x, pos.y));
+                    if inside(pos) {
+                        item.set_position(vec2i(pos.0, pos.1));
+                        region_undo.record_item(id, Some(before), Some(item.clone()));
+                    } else {
+                        self.items.remove(&id);
+                        region_undo.record_item(id, Some(before), None);
+                    }
+                }
+            }
+        }
+
+        region_undo.record_size((self.width, self.height), (width, height));
+        self.width = width;
+        self.height = height;
+
+        region_undo
+    }
+
     /// Create a region from json.
     pub fn from_json(json: &str) -> Self {
         serde_json::from_str(json).unwrap_or(Region::new())
@@ -99,6 +540,112 @@ impl Region {
     }
 }
 
+/// The state of a recursive shadowcasting pass over one octant.
+struct ShadowCaster<'a, F> {
+    region: &'a Region,
+    origin: Vec2i,
+    radius: i32,
+    transform: (i32, i32, i32, i32),
+    blocks_vision: &'a F,
+    visible: FxHashSet<(i32, i32)>,
+}
+
+impl<F> ShadowCaster<'_, F>
+where
+    F: Fn(Vec2i) -> bool,
+{
+    /// Scans the rows of the octant starting at the given row between the start and end
+    /// slopes, recursing for the light passing around blocking tiles.
+    fn cast(&mut self, row: i32, mut start: f32, end: f32) {
+        if start < end {
+            return;
+        }
+
+        let (xx, xy, yx, yy) = self.transform;
+        let radius_squared = self.radius * self.radius;
+        let mut new_start = 0.0;
+
+        for j in row..=self.radius {
+            let dy = -j;
+            let mut blocked = false;
+
+            for dx in -j..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+                if start < right_slope {
+                    continue;
+                } else if end > left_slope {
+                    break;
+                }
+
+                let x = self.origin.x + dx * xx + dy * xy;
+                let y = self.origin.y + dx * yx + dy * yy;
+
+                let inside = x >= 0 && y >= 0 && x < self.region.width && y < self.region.height;
+                if inside && dx * dx + dy * dy <= radius_squared {
+                    self.visible.insert((x, y));
+                }
+
+                let opaque = !inside || (self.blocks_vision)(vec2i(x, y));
+                if blocked {
+                    if opaque {
+                        new_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = new_start;
+                    }
+                } else if opaque && j < self.radius {
+                    blocked = true;
+                    self.cast(j + 1, start, left_slope);
+                    new_start = right_slope;
+                }
+            }
+
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+/// The part of a region which stays in place when the region is resized.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum RegionAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl RegionAnchor {
+    pub fn from_index(index: u8) -> Option<RegionAnchor> {
+        match index {
+            0 => Some(RegionAnchor::TopLeft),
+            1 => Some(RegionAnchor::TopRight),
+            2 => Some(RegionAnchor::BottomLeft),
+            3 => Some(RegionAnchor::BottomRight),
+            4 => Some(RegionAnchor::Center),
+            _ => None,
+        }
+    }
+
+    /// Returns the offset by which the content moves when resizing from the old to the new
+    /// size, given as (width, height).
+    pub fn offset(&self, old: (i32, i32), new: (i32, i32)) -> Vec2i {
+        let dx = new.0 - old.0;
+        let dy = new.1 - old.1;
+        match self {
+            RegionAnchor::TopLeft => vec2i(0, 0),
+            RegionAnchor::TopRight => vec2i(dx, 0),
+            RegionAnchor::BottomLeft => vec2i(0, dy),
+            RegionAnchor::BottomRight => vec2i(dx, dy),
+            RegionAnchor::Center => vec2i(dx / 2, dy / 2),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
 pub enum Layer2DRole {
     Ground,
@@ -124,3 +671,355 @@ impl RegionTile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::tests::character_at;
+
+    /// Returns a region of the given size without any content.
+    fn region(width: i32, height: i32) -> Region {
+        let mut region = Region::new();
+        region.width = width;
+        region.height = height;
+        region
+    }
+
+    /// Returns a 4x4 region with ground tiles and an area in the top left and bottom right
+    /// corners and a character in the bottom right corner, along with the character id.
+    fn corners() -> (Region, Uuid) {
+        let mut region = region(4, 4);
+        region.set_tile((0, 0), Layer2DRole::Ground, Some(Uuid::new_v4()));
+        region.set_tile((3, 3), Layer2DRole::Ground, Some(Uuid::new_v4()));
+
+        let mut area = Area::new();
+        area.area.insert((0, 0));
+        area.area.insert((3, 3));
+        region.areas.insert(area.id, area);
+
+        let character = character_at(3, 3);
+        let id = character.id;
+        region.characters.insert(id, character);
+
+        (region, id)
+    }
+
+    /// Returns the sorted positions of the tiles and of the area of the region.
+    fn content(region: &Region) -> (Vec<(i32, i32)>, Vec<(i32, i32)>) {
+        let mut tiles: Vec<(i32, i32)> = region.tiles.keys().copied().collect();
+        tiles.sort();
+        let mut area: Vec<(i32, i32)> = region
+            .areas
+            .values()
+            .flat_map(|area| area.area.iter().copied())
+            .collect();
+        area.sort();
+        (tiles, area)
+    }
+
+    /// Returns a movement cost closure for the region treating the given positions as walls.
+    fn walls(region: &Region, walls: &[(i32, i32)]) -> impl Fn(Vec2i) -> Option<i32> {
+        let (width, height) = (region.width, region.height);
+        let walls: FxHashSet<(i32, i32)> = walls.iter().copied().collect();
+        move |pos: Vec2i| {
+            let inside = pos.x >= 0 && pos.y >= 0 && pos.x < width && pos.y < height;
+            if inside && !walls.contains(&(pos.x, pos.y)) {
+                Some(1)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn find_path_in_the_open() {
+        let region = region(10, 10);
+        let path = region
+            .find_path(vec2i(1, 1), vec2i(4, 3), walls(&region, &[]))
+            .unwrap();
+
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.first(), Some(&vec2i(1, 1)));
+        assert_eq!(path.last(), Some(&vec2i(4, 3)));
+        for step in path.windows(2) {
+            let distance = (step[1].x - step[0].x).abs() + (step[1].y - step[0].y).abs();
+            assert_eq!(distance, 1);
+        }
+    }
+
+    #[test]
+    fn find_path_around_a_wall() {
+        let region = region(5, 5);
+        let wall = [(2, 0), (2, 1), (2, 2), (2, 3)];
+        let path = region
+            .find_path(vec2i(0, 0), vec2i(4, 0), walls(&region, &wall))
+            .unwrap();
+
+        assert!(path.contains(&vec2i(2, 4)));
+        assert!(path.iter().all(|pos| !wall.contains(&(pos.x, pos.y))));
+    }
+
+    #[test]
+    fn find_path_prefers_cheaper_tiles() {
+        let region = region(3, 3);
+        let mud = |pos: Vec2i| {
+            if region.contains(pos) {
+                Some(if pos.y == 0 { 10 } else { 1 })
+            } else {
+                None
+            }
+        };
+        let path = region.find_path(vec2i(0, 0), vec2i(2, 0), mud).unwrap();
+        assert!(path.contains(&vec2i(1, 1)));
+    }
+
+    #[test]
+    fn find_path_to_a_blocked_destination() {
+        let region = region(5, 5);
+        let path = region.find_path(vec2i(0, 0), vec2i(3, 0), walls(&region, &[(3, 0)]));
+        assert_eq!(path.map(|path| path.len()), Some(4));
+    }
+
+    #[test]
+    fn find_path_without_a_way() {
+        let region = region(5, 5);
+        let wall = [(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)];
+        assert!(region
+            .find_path(vec2i(0, 0), vec2i(4, 0), walls(&region, &wall))
+            .is_none());
+        assert!(region
+            .find_path(vec2i(0, 0), vec2i(7, 0), walls(&region, &[]))
+            .is_none());
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_in_between() {
+        let region = region(10, 10);
+        let wall = |pos: Vec2i| pos == vec2i(2, 2);
+
+        assert!(region.line_of_sight(vec2i(0, 0), vec2i(4, 4), |_| false));
+        assert!(!region.line_of_sight(vec2i(0, 0), vec2i(4, 4), wall));
+        assert!(region.line_of_sight(vec2i(0, 0), vec2i(2, 2), wall));
+        assert!(region.line_of_sight(vec2i(2, 2), vec2i(4, 4), wall));
+        assert!(region.line_of_sight(vec2i(0, 4), vec2i(4, 0), wall));
+    }
+
+    #[test]
+    fn field_of_view_within_the_radius() {
+        let region = region(11, 11);
+        let visible = region.field_of_view(vec2i(5, 5), 3, |_| false);
+
+        assert!(visible.contains(&(5, 5)));
+        assert!(visible.contains(&(5, 2)));
+        assert!(visible.contains(&(8, 5)));
+        assert!(visible.contains(&(7, 7)));
+        assert!(!visible.contains(&(5, 1)));
+        assert!(!visible.contains(&(8, 8)));
+    }
+
+    #[test]
+    fn field_of_view_stops_at_walls() {
+        let region = region(11, 11);
+        let visible = region.field_of_view(vec2i(5, 5), 5, |pos| pos.x == 7);
+
+        assert!(visible.contains(&(6, 5)));
+        assert!(visible.contains(&(7, 5)));
+        assert!(!visible.contains(&(8, 5)));
+        assert!(!visible.contains(&(9, 6)));
+        assert!(visible.contains(&(3, 5)));
+    }
+
+    #[test]
+    fn field_of_view_stays_inside_the_region() {
+        let region = region(4, 4);
+        let visible = region.field_of_view(vec2i(0, 0), 10, |_| false);
+
+        assert_eq!(visible.len(), 16);
+        assert!(visible.iter().all(|(x, y)| region.contains(vec2i(*x, *y))));
+    }
+
+    #[test]
+    fn blocks_vision_by_flags_or_blocking_walls() {
+        let mut region = region(5, 1);
+        let mut tiles = FxHashMap::default();
+        let mut tile_flags = FxHashMap::default();
+
+        let mut add_tile = |blocking: bool, flags: TileFlags| {
+            let mut tile = TheRGBATile::new();
+            tile.id = Uuid::new_v4();
+            tile.blocking = blocking;
+            let id = tile.id;
+            tiles.insert(id, tile);
+            tile_flags.insert(id, flags);
+            id
+        };
+
+        let opaque = TileFlags {
+            blocks_vision: true,
+            ..Default::default()
+        };
+        let lit = TileFlags {
+            light: 2,
+            ..Default::default()
+        };
+
+        let old_wall = add_tile(true, TileFlags::default());
+        let window = add_tile(true, lit);
+        let water = add_tile(true, TileFlags::default());
+        let fog = add_tile(false, opaque);
+
+        region.set_tile((0, 0), Layer2DRole::Wall, Some(old_wall));
+        region.set_tile((1, 0), Layer2DRole::Wall, Some(window));
+        region.set_tile((2, 0), Layer2DRole::Ground, Some(water));
+        region.set_tile((3, 0), Layer2DRole::Ground, Some(fog));
+
+        let blocks = |x: i32| region.blocks_vision(vec2i(x, 0), &tiles, &tile_flags);
+        assert!(blocks(0));
+        assert!(!blocks(1));
+        assert!(!blocks(2));
+        assert!(blocks(3));
+        assert!(!blocks(4));
+        assert!(blocks(5));
+        assert!(blocks(-1));
+    }
+
+    #[test]
+    fn rect_positions_filled_and_outlined() {
+        let filled = Region::rect_positions(vec2i(3, 2), vec2i(1, 0), false);
+        assert_eq!(filled.len(), 9);
+        assert_eq!(filled.first(), Some(&(1, 0)));
+        assert_eq!(filled.last(), Some(&(3, 2)));
+
+        let outline = Region::rect_positions(vec2i(0, 0), vec2i(3, 3), true);
+        assert_eq!(outline.len(), 12);
+        assert!(!outline.contains(&(1, 1)));
+        assert!(!outline.contains(&(2, 2)));
+    }
+
+    #[test]
+    fn line_positions_include_both_ends() {
+        assert_eq!(
+            Region::line_positions(vec2i(2, 2), vec2i(2, 2)),
+            vec![(2, 2)]
+        );
+        assert_eq!(
+            Region::line_positions(vec2i(0, 0), vec2i(3, 0)),
+            vec![(0, 0), (1, 0), (2, 0), (3, 0)]
+        );
+        assert_eq!(
+            Region::line_positions(vec2i(3, 3), vec2i(0, 0)),
+            vec![(3, 3), (2, 2), (1, 1), (0, 0)]
+        );
+
+        let line = Region::line_positions(vec2i(0, 0), vec2i(5, 2));
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.first(), Some(&(0, 0)));
+        assert_eq!(line.last(), Some(&(5, 2)));
+        for step in line.windows(2) {
+            assert_eq!(step[1].0 - step[0].0, 1);
+            assert!((0..=1).contains(&(step[1].1 - step[0].1)));
+        }
+    }
+
+    #[test]
+    fn flood_fill_positions_of_the_same_tile() {
+        let mut region = region(4, 4);
+        let grass = Uuid::new_v4();
+        let stone = Uuid::new_v4();
+
+        for (x, y) in Region::rect_positions(vec2i(0, 0), vec2i(3, 3), false) {
+            region.set_tile((x, y), Layer2DRole::Ground, Some(grass));
+        }
+        for y in 0..4 {
+            region.set_tile((2, y), Layer2DRole::Ground, Some(stone));
+        }
+
+        let mut left = region.flood_fill_positions(vec2i(0, 0), Layer2DRole::Ground);
+        left.sort_by_key(|(x, y)| (*y, *x));
+        let expected = Region::rect_positions(vec2i(0, 0), vec2i(1, 3), false);
+        assert_eq!(left, expected);
+
+        let right = region.flood_fill_positions(vec2i(3, 0), Layer2DRole::Ground);
+        assert_eq!(right.len(), 4);
+
+        // Empty layers are filled as one connected area.
+        let walls = region.flood_fill_positions(vec2i(1, 1), Layer2DRole::Wall);
+        assert_eq!(walls.len(), 16);
+
+        assert!(region
+            .flood_fill_positions(vec2i(4, 0), Layer2DRole::Ground)
+            .is_empty());
+    }
+
+    #[test]
+    fn resize_keeps_the_content_at_the_anchor() {
+        let (mut region, id) = corners();
+        let region_undo = region.resize(6, 6, RegionAnchor::BottomRight);
+
+        assert_eq!((region.width, region.height), (6, 6));
+        let expected = vec![(2, 2), (5, 5)];
+        assert_eq!(content(&region), (expected.clone(), expected));
+        assert_eq!(region.characters[&id].position(), Some(vec2i(5, 5)));
+        assert_eq!(region_undo.size, Some(((4, 4), (6, 6))));
+
+        assert_eq!(RegionAnchor::Center.offset((4, 4), (8, 6)), vec2i(2, 1));
+        assert_eq!(RegionAnchor::TopLeft.offset((4, 4), (8, 6)), vec2i(0, 0));
+    }
+
+    #[test]
+    fn resize_removes_content_outside_and_can_be_undone() {
+        let (mut region, id) = corners();
+        let original = region.clone();
+        let region_undo = region.resize(2, 2, RegionAnchor::TopLeft);
+
+        assert_eq!((region.width, region.height), (2, 2));
+        assert_eq!(content(&region), (vec![(0, 0)], vec![(0, 0)]));
+        assert!(!region.characters.contains_key(&id));
+
+        region_undo.inverted().apply(&mut region);
+        assert_eq!(region, original);
+
+        region_undo.apply(&mut region);
+        assert_eq!((region.width, region.height), (2, 2));
+        assert!(region.characters.is_empty());
+    }
+
+    #[test]
+    fn shift_content_keeps_the_size() {
+        let (mut region, id) = corners();
+        let region_undo = region.shift_content(vec2i(1, 0));
+
+        assert_eq!((region.width, region.height), (4, 4));
+        assert_eq!(content(&region), (vec![(1, 0)], vec![(1, 0)]));
+        assert!(!region.characters.contains_key(&id));
+        assert_eq!(region_undo.size, None);
+        assert!(region_undo.characters[0].2.is_none());
+    }
+
+    #[test]
+    fn crop_to_content_shrinks_to_the_bounds() {
+        let mut region = region(10, 10);
+        region.set_tile((2, 3), Layer2DRole::Ground, Some(Uuid::new_v4()));
+        region.set_tile((4, 5), Layer2DRole::Wall, Some(Uuid::new_v4()));
+        let character = character_at(6, 4);
+        let id = character.id;
+        region.characters.insert(id, character);
+
+        let original = region.clone();
+        let region_undo = region.crop_to_content();
+
+        assert_eq!((region.width, region.height), (5, 3));
+        assert_eq!(content(&region).0, vec![(0, 0), (2, 2)]);
+        assert_eq!(region.characters[&id].position(), Some(vec2i(4, 1)));
+
+        region_undo.inverted().apply(&mut region);
+        assert_eq!(region, original);
+    }
+
+    #[test]
+    fn crop_to_content_of_an_empty_region() {
+        let mut region = region(10, 10);
+        assert!(region.crop_to_content().is_empty());
+        assert_eq!((region.width, region.height), (10, 10));
+    }
+}
@@ -6,6 +6,9 @@ pub struct Renderer {
     pub textures: FxHashMap<Uuid, TheRGBATile>,
     pub tiles: FxHashMap<(i32, i32, i32), Uuid>,
     pub position: Vec3f,
+
+    /// The lighting of the region, the region is fully lit if None.
+    pub light_map: Option<LightMap>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -15,6 +18,7 @@ impl Renderer {
             textures: FxHashMap::default(),
             tiles: FxHashMap::default(),
             position: Vec3f::zero(),
+            light_map: None,
         }
     }
 
@@ -104,6 +108,13 @@ impl Renderer {
                         pixel = p;
                     }
                 }
+                if let Some(light_map) = &self.light_map {
+                    let hp = ray.at(dist);
+                    let brightness = light_map.brightness_at(vec2f(hp.x, hp.z));
+                    for c in &mut pixel[0..3] {
+                        *c = (*c as f32 * brightness) as u8;
+                    }
+                }
                 break;
             }
             // if let Some(tile) = self.project.tiles.get(&(key.x, key.y, key.z)) {
@@ -183,6 +194,11 @@ impl Renderer {
         self.textures = tiles;
     }
 
+    /// Sets the lighting of the region.
+    pub fn set_light_map(&mut self, light_map: Option<LightMap>) {
+        self.light_map = light_map;
+    }
+
     pub fn set_position(&mut self, position: Vec3i) {
         self.position = position.into();
     }
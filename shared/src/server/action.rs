@@ -0,0 +1,66 @@
+use theframework::prelude::*;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PlayerDirection {
+    None,
+    North,
+    East,
+    South,
+    West,
+}
+
+impl PlayerDirection {
+    /// Returns the offset of the direction in grid coordinates.
+    pub fn to_offset(&self) -> Vec2i {
+        match self {
+            PlayerDirection::None => vec2i(0, 0),
+            PlayerDirection::North => vec2i(0, -1),
+            PlayerDirection::East => vec2i(1, 0),
+            PlayerDirection::South => vec2i(0, 1),
+            PlayerDirection::West => vec2i(-1, 0),
+        }
+    }
+
+    /// Returns the name of the direction as seen by the scripts.
+    pub fn name(&self) -> String {
+        match self {
+            PlayerDirection::None => "none",
+            PlayerDirection::North => "north",
+            PlayerDirection::East => "east",
+            PlayerDirection::South => "south",
+            PlayerDirection::West => "west",
+        }
+        .to_string()
+    }
+}
+
+/// An action of a player controlled character, e.g. "move", "use", "take" or "attack".
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct PlayerAction {
+    pub action: String,
+    pub direction: PlayerDirection,
+}
+
+impl PlayerAction {
+    pub fn new(action: &str, direction: PlayerDirection) -> Self {
+        Self {
+            action: action.to_string(),
+            direction,
+        }
+    }
+
+    /// Returns true if this is a move action.
+    pub fn is_move(&self) -> bool {
+        self.action == "move"
+    }
+}
+
+/// A player action as recorded in the input log of the server. Replaying the input log of a
+/// run with the same seed reproduces the run.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct InputEvent {
+    /// The tick at which the action was received, it is executed during the following tick.
+    pub tick: i64,
+    pub character: Uuid,
+    pub action: PlayerAction,
+}
@@ -0,0 +1,177 @@
+use crate::prelude::*;
+use crate::server::prelude::*;
+use theframework::prelude::*;
+
+/// The hit points of characters which do not define "max_hp".
+pub const DEFAULT_MAX_HP: i32 = 10;
+
+/// The attack of characters which do not define "attack".
+pub const DEFAULT_ATTACK: &str = "1d4";
+
+/// The maximum number of dice and of sides per die in a dice term.
+pub const MAX_DICE: i32 = 1000;
+
+/// Rolls a dice expression like "2d6+3", "d20-1" or "1d8+1d4". Plain numbers are returned as
+/// is. Returns None if the expression is invalid, a term exceeds MAX_DICE dice or sides, or
+/// the result overflows.
+pub fn roll<R: Rng>(expression: &str, rng: &mut R) -> Option<i32> {
+    let expression = expression
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+
+    if expression.is_empty() {
+        return None;
+    }
+
+    let mut total: i32 = 0;
+    let mut sign: i32 = 1;
+    let mut term = String::new();
+
+    for c in expression.chars().chain(std::iter::once('+')) {
+        if c == '+' || c == '-' {
+            if term.is_empty() {
+                // A leading sign.
+                if c == '-' {
+                    sign = -sign;
+                }
+                continue;
+            }
+            let value = roll_term(&term, rng)?.checked_mul(sign)?;
+            total = total.checked_add(value)?;
+            term.clear();
+            sign = if c == '-' { -1 } else { 1 };
+        } else {
+            term.push(c);
+        }
+    }
+
+    Some(total)
+}
+
+/// Rolls a single term of a dice expression, i.e. "2d6", "d20" or "3".
+fn roll_term<R: Rng>(term: &str, rng: &mut R) -> Option<i32> {
+    if let Some((count, sides)) = term.split_once('d') {
+        let count: i32 = if count.is_empty() {
+            1
+        } else {
+            count.parse().ok()?
+        };
+        let sides: i32 = sides.parse().ok()?;
+        if !(0..=MAX_DICE).contains(&count) || !(1..=MAX_DICE).contains(&sides) {
+            return None;
+        }
+
+        // At most MAX_DICE * MAX_DICE, which cannot overflow.
+        let mut sum = 0;
+        for _ in 0..count {
+            sum += rng.gen_range(1..=sides);
+        }
+        Some(sum)
+    } else {
+        term.parse().ok()
+    }
+}
+
+/// Returns the hit points and the maximum hit points of the character object. Characters
+/// without an "hp" property have full health.
+pub fn hit_points(object: &TheCodeObject) -> (i32, i32) {
+    let max_hp = object
+        .get(&"max_hp".into())
+        .and_then(|v| v.to_i32())
+        .unwrap_or(DEFAULT_MAX_HP);
+    let hp = object
+        .get(&"hp".into())
+        .and_then(|v| v.to_i32())
+        .unwrap_or(max_hp);
+    (hp, max_hp)
+}
+
+/// Changes the hit points of the character object by the given amount, clamped to the range
+/// of 0 to the maximum hit points. Returns the new hit points.
+pub fn change_hit_points(object: &mut TheCodeObject, amount: i32) -> i32 {
+    let (hp, max_hp) = hit_points(object);
+    let hp = hp.saturating_add(amount).clamp(0, max_hp);
+    object.set("hp".to_string(), TheValue::Int(hp));
+    hp
+}
+
+/// Records the change of hit points of a character in the update of the context. Characters
+/// whose hit points dropped to zero are queued for dying.
+pub fn record_hit_points(
+    context: &mut RegionContext,
+    character: Uuid,
+    hp: i32,
+    max_hp: i32,
+    effect: CharacterEffect,
+) {
+    if let Some(cu) = context.update.characters.get_mut(&character) {
+        cu.hp = hp;
+        cu.max_hp = max_hp;
+        cu.effects.push(effect);
+
+        if hp == 0 && !cu.dead && !context.deaths.contains(&character) {
+            context.deaths.push(character);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn rng() -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(42)
+    }
+
+    #[test]
+    fn roll_plain_numbers_and_signs() {
+        assert_eq!(roll("3", &mut rng()), Some(3));
+        assert_eq!(roll("-3", &mut rng()), Some(-3));
+        assert_eq!(roll("10 - 4 + 1", &mut rng()), Some(7));
+        assert_eq!(roll("--2", &mut rng()), Some(2));
+    }
+
+    #[test]
+    fn roll_dice_in_range() {
+        let mut rng = rng();
+        for _ in 0..100 {
+            let value = roll("2d6+3", &mut rng).unwrap();
+            assert!((5..=15).contains(&value));
+
+            let value = roll("d20-1", &mut rng).unwrap();
+            assert!((0..=19).contains(&value));
+
+            let value = roll("1D8+1d4", &mut rng).unwrap();
+            assert!((2..=12).contains(&value));
+        }
+        assert_eq!(roll("0d6", &mut rng), Some(0));
+    }
+
+    #[test]
+    fn roll_is_deterministic_for_a_seed() {
+        let a: Vec<_> = (0..10).scan(rng(), |r, _| roll("3d6", r)).collect();
+        let b: Vec<_> = (0..10).scan(rng(), |r, _| roll("3d6", r)).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn roll_rejects_invalid_expressions() {
+        assert_eq!(roll("", &mut rng()), None);
+        assert_eq!(roll("abc", &mut rng()), None);
+        assert_eq!(roll("2d", &mut rng()), None);
+        assert_eq!(roll("2d0", &mut rng()), None);
+        assert_eq!(roll("-1d6d", &mut rng()), None);
+    }
+
+    #[test]
+    fn roll_rejects_too_many_dice_and_overflow() {
+        assert_eq!(roll("2000000000d6", &mut rng()), None);
+        assert_eq!(roll("2d2000000000", &mut rng()), None);
+        assert_eq!(roll("2000000000+2000000000", &mut rng()), None);
+        assert_eq!(roll("-2000000000-2000000000", &mut rng()), None);
+        assert!(roll("1000d1000", &mut rng()).is_some());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::server::prelude::*;
 use theframework::prelude::*;
 
 /// This gives context to the server of the editing state for live highlighting.
@@ -13,6 +14,15 @@ pub struct ServerContext {
 
     /// The currently selected codegrid in the code editor.
     pub curr_grid_id: Option<Uuid>,
+
+    /// The character instance controlled by the user in Play mode.
+    pub curr_player: Option<Uuid>,
+
+    /// Only show the tiles in the field of view of the player.
+    pub fov_mode: bool,
+
+    /// The compile errors reported by the server, highlighted in the code editor.
+    pub diagnostics: Vec<CompileDiagnostic>,
 }
 
 impl Default for ServerContext {
@@ -29,6 +39,11 @@ impl ServerContext {
             curr_character_instance: None,
 
             curr_grid_id: None,
+
+            curr_player: None,
+            fov_mode: false,
+
+            diagnostics: vec![],
         }
     }
 }
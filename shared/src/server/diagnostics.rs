@@ -0,0 +1,44 @@
+use theframework::prelude::*;
+
+/// A compile error of a grid, reported by the server.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CompileDiagnostic {
+    /// The id of the bundle (character, item, code or instance) containing the grid.
+    pub bundle_id: Uuid,
+    pub bundle_name: String,
+
+    pub grid_id: Uuid,
+    pub grid_name: String,
+
+    /// The coordinate of the failing atom in the grid.
+    pub location: (u16, u16),
+    pub message: String,
+}
+
+impl CompileDiagnostic {
+    pub fn new(
+        bundle_id: Uuid,
+        bundle_name: String,
+        grid: &TheCodeGrid,
+        error: &TheCompilerError,
+    ) -> Self {
+        Self {
+            bundle_id,
+            bundle_name,
+            grid_id: grid.id,
+            grid_name: grid.name.clone(),
+            location: error.location,
+            message: error.message.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for CompileDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} / {} ({}, {}): {}",
+            self.bundle_name, self.grid_name, self.location.0, self.location.1, self.message
+        )
+    }
+}
@@ -0,0 +1,16 @@
+use theframework::prelude::*;
+
+/// An event sent by a character to other characters of the region with SendEvent. It is
+/// delivered in the next tick by executing the on_<name> function of the receivers.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct CharacterEvent {
+    /// The name of the receiving character instances.
+    pub target: String,
+
+    /// The name of the event.
+    pub name: String,
+    pub value: TheValue,
+
+    /// The sending character instance.
+    pub sender: Uuid,
+}
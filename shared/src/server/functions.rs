@@ -1,5 +1,7 @@
 use crate::prelude::*;
-use crate::server::{REGIONS, RNG, TILES, UPDATES};
+use crate::server::combat;
+use crate::server::vision;
+use crate::server::prelude::*;
 use theframework::prelude::*;
 
 pub fn add_compiler_functions(compiler: &mut TheCompiler) {
@@ -7,14 +9,19 @@ pub fn add_compiler_functions(compiler: &mut TheCompiler) {
     compiler.add_external_call(
         "RandWalk".to_string(),
         |stack, data, sandbox| {
-            let region_id = sandbox.id;
-            if let Some(region) = REGIONS.read().unwrap().get(&region_id) {
-                if let Some(object) = sandbox.get_self_mut() {
-                    if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+            let mut moved = None;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                    let object_id = object.id;
+
+                    // Release the context before executing the sub functions below, they may
+                    // access it themselves.
+                    moved = Some(with_context(|context| {
                         let mut x = p.x;
                         let mut y = p.y;
 
-                        let dir = RNG.lock().unwrap().gen_range(0..=4);
+                        let dir = context.rng.gen_range(0..=4);
 
                         if dir == 0 {
                             x += 1.0;
@@ -26,34 +33,904 @@ pub fn add_compiler_functions(compiler: &mut TheCompiler) {
                             y -= 1.0;
                         }
 
-                        if region.can_move_to(vec3f(x, y, p.z), &TILES.read().unwrap()) {
-                            let old_position = *p;
-
+                        if context.try_move(object_id, p.xy(), vec2f(x, y)) {
                             *p = vec3f(x, y, p.z);
+                            true
+                        } else {
+                            false
+                        }
+                    }));
+                }
+            }
+
+            if let Some(moved) = moved {
+                if sandbox.debug_mode {
+                    sandbox.set_debug_value(data.location, (None, TheValue::Bool(moved)));
+                }
+                if moved && !data.sub_functions.is_empty() {
+                    _ = data.sub_functions[0].execute(sandbox).pop();
+                }
+                stack.push(TheValue::Bool(moved));
+            }
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
 
-                            if let Some(update) = UPDATES.write().unwrap().get_mut(&region_id) {
-                                if let Some(cu) = update.characters.get_mut(&object.id) {
-                                    cu.position = vec2f(x, y);
-                                    cu.moving = Some((old_position.xy(), cu.position));
-                                    cu.move_delta = 0.0;
+    // GoTo
+    compiler.add_external_call(
+        "GoTo".to_string(),
+        |stack, data, sandbox| {
+            let target = stack.pop();
+            let mut reached = None;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                    let object_id = object.id;
+                    let position = vec2i(p.x as i32, p.y as i32);
+
+                    reached = with_context(|context| {
+                        // The target is either a position or the name of a character. Characters
+                        // are reached as soon as we are standing next to them.
+                        let (target, close_in) = match &target {
+                            Some(TheValue::Position(t)) => (vec2i(t.x as i32, t.y as i32), false),
+                            Some(TheValue::Text(name)) => {
+                                let character = context
+                                    .update
+                                    .characters
+                                    .iter()
+                                    .find(|(id, c)| **id != object_id && c.name == *name)
+                                    .map(|(_, c)| c.position);
+                                if let Some(c) = character {
+                                    (vec2i(c.x as i32, c.y as i32), true)
+                                } else {
+                                    return None;
                                 }
                             }
+                            _ => return None,
+                        };
 
-                            if sandbox.debug_mode {
-                                sandbox
-                                    .set_debug_value(data.location, (None, TheValue::Bool(true)));
+                        if position == target {
+                            context.paths.remove(&object_id);
+                            context.unreachable.remove(&object_id);
+                            return Some(true);
+                        }
+
+                        // A blocked or occupied target position can not be reached, no need
+                        // to search for a path.
+                        let goal = vec2f(target.x as f32, target.y as f32);
+                        if !close_in && !context.can_move_to(object_id, goal) {
+                            context.paths.remove(&object_id);
+                            return Some(false);
+                        }
+
+                        // After a failed search wait a few ticks before searching again.
+                        let tick = context.world.tick_counter;
+                        if let Some((t, retry)) = context.unreachable.get(&object_id) {
+                            if *t == target && *retry > tick {
+                                return Some(false);
+                            }
+                        }
+
+                        // Reuse the cached path if it still leads to the target from here,
+                        // otherwise compute a new one.
+                        let cached = context.paths.get(&object_id).map_or(false, |(t, path)| {
+                            *t == target && path.len() > 1 && path[0] == position
+                        });
+                        if !cached {
+                            if let Some(path) = context.find_path(object_id, position, target) {
+                                context.unreachable.remove(&object_id);
+                                context.paths.insert(object_id, (target, path));
+                            } else {
+                                context.paths.remove(&object_id);
+                                context
+                                    .unreachable
+                                    .insert(object_id, (target, tick + PATH_RETRY_TICKS));
+                                return Some(false);
+                            }
+                        }
+
+                        let next = {
+                            let (_, path) = context.paths.get(&object_id)?;
+                            if close_in && path.len() <= 2 {
+                                context.paths.remove(&object_id);
+                                return Some(true);
+                            }
+                            path[1]
+                        };
+
+                        let to = vec2f(next.x as f32, next.y as f32);
+                        if context.try_move(object_id, p.xy(), to) {
+                            if let Some((_, path)) = context.paths.get_mut(&object_id) {
+                                path.remove(0);
                             }
-                            if !data.sub_functions.is_empty() {
-                                _ = data.sub_functions[0].execute(sandbox).pop();
+                            *p = vec3f(to.x, to.y, p.z);
+
+                            Some(next == target)
+                        } else {
+                            // The route got blocked, compute a new path on the next call.
+                            context.paths.remove(&object_id);
+                            Some(false)
+                        }
+                    });
+                }
+            }
+
+            let reached = reached.unwrap_or(false);
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(reached)));
+            }
+            if reached && !data.sub_functions.is_empty() {
+                _ = data.sub_functions[0].execute(sandbox).pop();
+            }
+            stack.push(TheValue::Bool(reached));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Teleport
+    compiler.add_external_call(
+        "Teleport".to_string(),
+        |stack, data, sandbox| {
+            let y = stack.pop().and_then(|v| v.to_i32()).unwrap_or(0) as f32;
+            let x = stack.pop().and_then(|v| v.to_i32()).unwrap_or(0) as f32;
+            let region_name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut teleported = false;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                let object_id = object.id;
+
+                // Inside the current region we can move right away if the destination can be
+                // entered and is free. Transfers to other regions are processed by the server
+                // after the tick, which checks the destination and keeps the character in
+                // place if it is blocked or occupied.
+                let (local, success) = with_context(|context| {
+                    if region_name.is_empty() || region_name == context.region.name {
+                        if context.can_move_to(object_id, vec2f(x, y)) {
+                            context.set_position(object_id, vec2f(x, y), false);
+                            (true, true)
+                        } else {
+                            (true, false)
+                        }
+                    } else {
+                        context
+                            .transfers
+                            .push((object_id, region_name.clone(), vec2f(x, y)));
+                        (false, true)
+                    }
+                });
+
+                if local && success {
+                    if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                        *p = vec3f(x, y, p.z);
+                    }
+                }
+                teleported = success;
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(teleported)));
+            }
+            stack.push(TheValue::Bool(teleported));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Take
+    compiler.add_external_call(
+        "Take".to_string(),
+        |stack, data, sandbox| {
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut taken = None;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                if let Some(TheValue::Position(p)) = object.get(&"position".into()) {
+                    let object_id = object.id;
+                    let pos = vec2i(p.x as i32, p.y as i32);
+
+                    taken = with_context(|context| {
+                        let item = context.item_at(pos, &name)?;
+                        context.update.items.remove(&item);
+                        context.inventories.entry(object_id).or_default().add(item);
+                        context
+                            .item_events
+                            .push((item, "on_take".to_string(), object_id));
+                        Some(item)
+                    });
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(taken.is_some())));
+            }
+            stack.push(TheValue::Bool(taken.is_some()));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Drop
+    compiler.add_external_call(
+        "Drop".to_string(),
+        |stack, data, sandbox| {
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut dropped = false;
+
+            let mut user = None;
+            if let Some(object) = sandbox.get_self_mut() {
+                if let Some(TheValue::Position(p)) = object.get(&"position".into()) {
+                    user = Some((object.id, *p));
+                }
+            }
+
+            if let Some((object_id, position)) = user {
+                if let Some(item) = find_inventory_item(sandbox, object_id, &name) {
+                    if let Some(item_object) = sandbox.objects.get_mut(&item) {
+                        item_object.set("position".to_string(), TheValue::Position(position));
+                        let item_update = ItemUpdate::from_object(item_object);
+
+                        with_context(|context| {
+                            if let Some(inventory) = context.inventories.get_mut(&object_id) {
+                                inventory.remove(&item);
                             }
-                            stack.push(TheValue::Bool(true));
-                        } else if sandbox.debug_mode {
-                            sandbox.set_debug_value(data.location, (None, TheValue::Bool(false)));
-                            stack.push(TheValue::Bool(false));
+                            context.update.items.insert(item, item_update);
+                            context
+                                .item_events
+                                .push((item, "on_drop".to_string(), object_id));
+                        });
+                        dropped = true;
+                    }
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(dropped)));
+            }
+            stack.push(TheValue::Bool(dropped));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Use
+    compiler.add_external_call(
+        "Use".to_string(),
+        |stack, data, sandbox| {
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut used = false;
+
+            if let Some(object_id) = sandbox.get_self_mut().map(|object| object.id) {
+                if let Some(item) = find_inventory_item(sandbox, object_id, &name) {
+                    with_context(|context| {
+                        context
+                            .item_events
+                            .push((item, "on_use".to_string(), object_id));
+                    });
+                    used = true;
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(used)));
+            }
+            stack.push(TheValue::Bool(used));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Equip
+    compiler.add_external_call(
+        "Equip".to_string(),
+        |stack, data, sandbox| {
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut equipped = false;
+
+            if let Some(object_id) = sandbox.get_self_mut().map(|object| object.id) {
+                if let Some(item) = find_inventory_item(sandbox, object_id, &name) {
+                    // Items define their slot in the "slot" property, by default they are
+                    // held in the hand.
+                    let slot = if let Some(TheValue::Text(slot)) = sandbox
+                        .objects
+                        .get(&item)
+                        .and_then(|o| o.get(&"slot".into()))
+                    {
+                        slot.clone()
+                    } else {
+                        "hand".to_string()
+                    };
+
+                    with_context(|context| {
+                        if let Some(inventory) = context.inventories.get_mut(&object_id) {
+                            inventory.equip(slot, item);
                         }
+                        context
+                            .item_events
+                            .push((item, "on_equip".to_string(), object_id));
+                    });
+                    equipped = true;
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(equipped)));
+            }
+            stack.push(TheValue::Bool(equipped));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Call
+    compiler.add_external_call(
+        "Call".to_string(),
+        |stack, data, sandbox| {
+            let arg3 = stack.pop().unwrap_or(TheValue::Empty);
+            let arg2 = stack.pop().unwrap_or(TheValue::Empty);
+            let arg1 = stack.pop().unwrap_or(TheValue::Empty);
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let (bundle, module) = CodeLibrary::split_function_name(&name);
+
+            // Take the package of the function out of the context, the context has to be
+            // released while the function executes. Only recursive calls, whose package is
+            // already taken, need a copy of the library package.
+            let function = with_context(|context| {
+                if context.call_depth >= MAX_CALL_DEPTH {
+                    context.errors.push(format!(
+                        "Call: maximum call depth of {} exceeded in {}",
+                        MAX_CALL_DEPTH, name
+                    ));
+                    return None;
+                }
+
+                let package = context
+                    .functions
+                    .remove(bundle)
+                    .or_else(|| context.library.get(bundle).cloned())?;
+                context.call_depth += 1;
+                Some((package, context.call_depth))
+            });
+
+            let mut result = TheValue::Empty;
+
+            if let Some((mut package, depth)) = function {
+                // The arguments are passed in the "args" object, the function stores its
+                // return value in the "result" property of it. "self" stays the caller. The
+                // args object only lives during the call, an id per call depth keeps runs
+                // reproducible.
+                let mut args = TheCodeObject::new();
+                args.id = Uuid::from_u128(depth as u128);
+                args.set("arg1".to_string(), arg1);
+                args.set("arg2".to_string(), arg2);
+                args.set("arg3".to_string(), arg3);
+                let args_id = args.id;
+
+                let previous = sandbox.aliases.get("args").cloned();
+                sandbox.add_object(args);
+                sandbox.aliases.insert("args".to_string(), args_id);
+
+                package.execute(module.to_string(), sandbox);
+
+                if let Some(args) = sandbox.objects.remove(&args_id) {
+                    if let Some(value) = args.get(&"result".into()) {
+                        result = value.clone();
                     }
                 }
+
+                if let Some(previous) = previous {
+                    sandbox.aliases.insert("args".to_string(), previous);
+                } else {
+                    sandbox.aliases.remove("args");
+                }
+
+                with_context(|context| {
+                    context.call_depth -= 1;
+                    context.functions.insert(bundle.to_string(), package);
+                });
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, result.clone()));
             }
+            stack.push(result);
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // SendEvent
+    compiler.add_external_call(
+        "SendEvent".to_string(),
+        |stack, data, sandbox| {
+            let value = stack.pop().unwrap_or(TheValue::Empty);
+            let name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+            let target = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut sent = false;
+
+            if let Some(sender) = sandbox.get_self_mut().map(|object| object.id) {
+                if !target.is_empty() && !name.is_empty() {
+                    with_context(|context| {
+                        context.events.push(CharacterEvent {
+                            target,
+                            name,
+                            value,
+                            sender,
+                        });
+                    });
+                    sent = true;
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(sent)));
+            }
+            stack.push(TheValue::Bool(sent));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // StartTimer
+    compiler.add_external_call(
+        "StartTimer".to_string(),
+        |stack, data, sandbox| {
+            let repeat = matches!(stack.pop(), Some(TheValue::Bool(true)));
+            let ticks = stack.pop().and_then(|v| v.to_i32()).unwrap_or(0).max(1) as i64;
+            let function = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut started = false;
+
+            if let Some(character) = sandbox.get_self_mut().map(|object| object.id) {
+                if !function.is_empty() {
+                    with_context(|context| {
+                        // Restarting a timer replaces the running one.
+                        context.timers.retain(|timer| {
+                            timer.character != character || timer.function != function
+                        });
+                        context.timers.push(Timer {
+                            character,
+                            tick: context.world.tick_counter + ticks,
+                            function,
+                            interval: if repeat { Some(ticks) } else { None },
+                        });
+                    });
+                    started = true;
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(started)));
+            }
+            stack.push(TheValue::Bool(started));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // StopTimer
+    compiler.add_external_call(
+        "StopTimer".to_string(),
+        |stack, data, sandbox| {
+            let function = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut stopped = false;
+
+            if let Some(character) = sandbox.get_self_mut().map(|object| object.id) {
+                stopped = with_context(|context| {
+                    let len = context.timers.len();
+                    context.timers.retain(|timer| {
+                        timer.character != character || timer.function != function
+                    });
+                    context.timers.len() != len
+                });
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(stopped)));
+            }
+            stack.push(TheValue::Bool(stopped));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // ScheduleAt
+    compiler.add_external_call(
+        "ScheduleAt".to_string(),
+        |stack, data, sandbox| {
+            let minutes = stack.pop().and_then(|v| v.to_i32()).unwrap_or(0) as i64;
+            let hours = stack.pop().and_then(|v| v.to_i32()).unwrap_or(0) as i64;
+            let function = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut scheduled = false;
+
+            if let Some(character) = sandbox.get_self_mut().map(|object| object.id) {
+                if !function.is_empty() {
+                    with_context(|context| {
+                        context.timers.retain(|timer| {
+                            timer.character != character || timer.function != function
+                        });
+                        context.timers.push(Timer {
+                            character,
+                            tick: context.world.tick_counter
+                                + context.world.ticks_until(hours, minutes),
+                            function,
+                            interval: Some(context.world.ticks_per_day()),
+                        });
+                    });
+                    scheduled = true;
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(scheduled)));
+            }
+            stack.push(TheValue::Bool(scheduled));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Hour
+    compiler.add_external_call(
+        "Hour".to_string(),
+        |stack, data, sandbox| {
+            let hours = with_context(|context| context.world.date().hours) as i32;
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(hours)));
+            }
+            stack.push(TheValue::Int(hours));
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Minute
+    compiler.add_external_call(
+        "Minute".to_string(),
+        |stack, data, sandbox| {
+            let minutes = with_context(|context| context.world.date().minutes) as i32;
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(minutes)));
+            }
+            stack.push(TheValue::Int(minutes));
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Day
+    compiler.add_external_call(
+        "Day".to_string(),
+        |stack, data, sandbox| {
+            let day = with_context(|context| context.world.date().day) as i32;
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(day)));
+            }
+            stack.push(TheValue::Int(day));
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // DayPhase
+    compiler.add_external_call(
+        "DayPhase".to_string(),
+        |stack, data, sandbox| {
+            let phase = with_context(|context| context.world.day_phase().name());
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Text(phase.clone())));
+            }
+            stack.push(TheValue::Text(phase));
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Roll
+    compiler.add_external_call(
+        "Roll".to_string(),
+        |stack, data, sandbox| {
+            let expression = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let value = with_context(|context| combat::roll(&expression, &mut context.rng))
+                .unwrap_or(0);
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(value)));
+            }
+            stack.push(TheValue::Int(value));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Attack
+    compiler.add_external_call(
+        "Attack".to_string(),
+        |stack, data, sandbox| {
+            let target_name = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut damage = 0;
+
+            let attacker = sandbox.get_self_mut().map(|object| {
+                let attack = if let Some(TheValue::Text(attack)) = object.get(&"attack".into()) {
+                    attack.clone()
+                } else {
+                    combat::DEFAULT_ATTACK.to_string()
+                };
+                (object.id, attack)
+            });
+
+            if let Some((attacker_id, attack)) = attacker {
+                // Find the living target next to the attacker, and roll the attack.
+                let target = with_context(|context| {
+                    let pos = context.update.characters.get(&attacker_id)?.position;
+
+                    let mut targets: Vec<Uuid> = context
+                        .update
+                        .characters
+                        .iter()
+                        .filter(|(id, cu)| {
+                            **id != attacker_id
+                                && !cu.dead
+                                && (target_name.is_empty() || cu.name == target_name)
+                                && (cu.position.x - pos.x).abs() <= 1.0
+                                && (cu.position.y - pos.y).abs() <= 1.0
+                        })
+                        .map(|(id, _)| *id)
+                        .collect();
+                    targets.sort();
+
+                    let target = *targets.first()?;
+                    let roll = combat::roll(&attack, &mut context.rng).unwrap_or(0);
+                    Some((target, roll))
+                });
+
+                if let Some((target, roll)) = target {
+                    if let Some(object) = sandbox.objects.get_mut(&target) {
+                        let defense = object
+                            .get(&"defense".into())
+                            .and_then(|v| v.to_i32())
+                            .unwrap_or(0);
+                        damage = (roll - defense).max(0);
+
+                        let hp = combat::change_hit_points(object, -damage);
+                        let (_, max_hp) = combat::hit_points(object);
+
+                        with_context(|context| {
+                            let effect = if damage > 0 {
+                                CharacterEffect::Damage(damage)
+                            } else {
+                                CharacterEffect::Miss
+                            };
+                            combat::record_hit_points(context, target, hp, max_hp, effect);
+                        });
+                    }
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(damage)));
+            }
+            stack.push(TheValue::Int(damage));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // TakeDamage
+    compiler.add_external_call(
+        "TakeDamage".to_string(),
+        |stack, data, sandbox| {
+            let expression = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut damage = 0;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                let character = object.id;
+                let dead = with_context(|context| context.dead.contains_key(&character));
+
+                if !dead {
+                    damage = with_context(|context| combat::roll(&expression, &mut context.rng))
+                        .unwrap_or(0)
+                        .max(0);
+
+                    let hp = combat::change_hit_points(object, -damage);
+                    let (_, max_hp) = combat::hit_points(object);
+
+                    with_context(|context| {
+                        let effect = CharacterEffect::Damage(damage);
+                        combat::record_hit_points(context, character, hp, max_hp, effect);
+                    });
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(damage)));
+            }
+            stack.push(TheValue::Int(damage));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // Heal
+    compiler.add_external_call(
+        "Heal".to_string(),
+        |stack, data, sandbox| {
+            let expression = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut healed = 0;
+
+            if let Some(object) = sandbox.get_self_mut() {
+                let character = object.id;
+                let dead = with_context(|context| context.dead.contains_key(&character));
+
+                // The dead can only be brought back by respawning.
+                if !dead {
+                    let amount =
+                        with_context(|context| combat::roll(&expression, &mut context.rng))
+                            .unwrap_or(0)
+                            .max(0);
+
+                    let (old_hp, max_hp) = combat::hit_points(object);
+                    let hp = combat::change_hit_points(object, amount);
+                    healed = hp - old_hp;
+
+                    with_context(|context| {
+                        let effect = CharacterEffect::Heal(healed);
+                        combat::record_hit_points(context, character, hp, max_hp, effect);
+                    });
+                }
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Int(healed)));
+            }
+            stack.push(TheValue::Int(healed));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // CanSee
+    compiler.add_external_call(
+        "CanSee".to_string(),
+        |stack, data, sandbox| {
+            let target = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut seen = false;
+            if let Some(viewer) = sandbox.get_self_mut().map(|object| object.id) {
+                seen = with_context(|context| {
+                    !vision::visible_characters(context, sandbox, viewer, &target).is_empty()
+                });
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Bool(seen)));
+            }
+            stack.push(TheValue::Bool(seen));
+
+            TheCodeNodeCallResult::Continue
+        },
+        vec![],
+    );
+
+    // NearestVisible
+    compiler.add_external_call(
+        "NearestVisible".to_string(),
+        |stack, data, sandbox| {
+            let role = if let Some(TheValue::Text(text)) = stack.pop() {
+                text
+            } else {
+                String::new()
+            };
+
+            let mut name = String::new();
+            if let Some(viewer) = sandbox.get_self_mut().map(|object| object.id) {
+                name = with_context(|context| {
+                    vision::visible_characters(context, sandbox, viewer, &role)
+                        .first()
+                        .and_then(|id| context.update.characters.get(id))
+                        .map(|cu| cu.name.clone())
+                        .unwrap_or_default()
+                });
+            }
+
+            if sandbox.debug_mode {
+                sandbox.set_debug_value(data.location, (None, TheValue::Text(name.clone())));
+            }
+            stack.push(TheValue::Text(name));
+
             TheCodeNodeCallResult::Continue
         },
         vec![],
@@ -116,3 +993,23 @@ pub fn add_compiler_functions(compiler: &mut TheCompiler) {
         vec![TheValue::Int(0), TheValue::Int(0)],
     );
 }
+
+/// Returns the first item in the inventory of the character with the given name. An empty
+/// name matches any item.
+fn find_inventory_item(sandbox: &TheCodeSandbox, character: Uuid, name: &str) -> Option<Uuid> {
+    let items = with_context(|context| {
+        context
+            .inventories
+            .get(&character)
+            .map(|inventory| inventory.items.clone())
+            .unwrap_or_default()
+    });
+
+    items.into_iter().find(|item| {
+        name.is_empty()
+            || matches!(
+                sandbox.objects.get(item).and_then(|o| o.get(&"name".into())),
+                Some(TheValue::Text(text)) if text == name
+            )
+    })
+}
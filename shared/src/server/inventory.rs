@@ -0,0 +1,109 @@
+use theframework::prelude::*;
+
+/// The items carried by a character.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Inventory {
+    /// The item instances in the order they were taken.
+    pub items: Vec<Uuid>,
+
+    /// The equipped item instances keyed by their slot.
+    pub equipped: FxHashMap<String, Uuid>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            items: vec![],
+            equipped: FxHashMap::default(),
+        }
+    }
+
+    /// Adds an item to the inventory.
+    pub fn add(&mut self, item: Uuid) {
+        if !self.items.contains(&item) {
+            self.items.push(item);
+        }
+    }
+
+    /// Removes an item from the inventory, unequipping it if necessary. Returns true if the
+    /// item was in the inventory.
+    pub fn remove(&mut self, item: &Uuid) -> bool {
+        self.equipped.retain(|_, id| id != item);
+        let len = self.items.len();
+        self.items.retain(|id| id != item);
+        self.items.len() != len
+    }
+
+    /// Returns true if the item is in the inventory.
+    pub fn contains(&self, item: &Uuid) -> bool {
+        self.items.contains(item)
+    }
+
+    /// Equips the item in the given slot, replacing the item currently equipped there.
+    pub fn equip(&mut self, slot: String, item: Uuid) {
+        self.equipped.retain(|_, id| *id != item);
+        self.equipped.insert(slot, item);
+    }
+
+    /// Returns true if the item is equipped.
+    pub fn is_equipped(&self, item: &Uuid) -> bool {
+        self.equipped.values().any(|id| id == item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_keeps_the_order_without_duplicates() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut inventory = Inventory::new();
+        inventory.add(a);
+        inventory.add(b);
+        inventory.add(a);
+
+        assert_eq!(inventory.items, vec![a, b]);
+        assert!(inventory.contains(&a));
+        assert!(!inventory.contains(&Uuid::new_v4()));
+    }
+
+    #[test]
+    fn remove_unequips_the_item() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut inventory = Inventory::new();
+        inventory.add(a);
+        inventory.add(b);
+        inventory.equip("hand".to_string(), a);
+
+        assert!(inventory.remove(&a));
+        assert!(!inventory.is_equipped(&a));
+        assert!(inventory.equipped.is_empty());
+        assert_eq!(inventory.items, vec![b]);
+        assert!(!inventory.remove(&a));
+    }
+
+    #[test]
+    fn equip_replaces_the_slot_and_moves_the_item() {
+        let (sword, axe) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut inventory = Inventory::new();
+        inventory.add(sword);
+        inventory.add(axe);
+
+        inventory.equip("hand".to_string(), sword);
+        inventory.equip("hand".to_string(), axe);
+        assert!(!inventory.is_equipped(&sword));
+        assert_eq!(inventory.equipped.get("hand"), Some(&axe));
+
+        inventory.equip("belt".to_string(), axe);
+        assert_eq!(inventory.equipped.len(), 1);
+        assert_eq!(inventory.equipped.get("belt"), Some(&axe));
+        assert_eq!(inventory.items.len(), 2);
+    }
+}
@@ -0,0 +1,75 @@
+use theframework::prelude::*;
+
+/// The maximum nesting depth of Call, deeper calls fail instead of overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 32;
+
+/// The code bundles of the project compiled into packages. Their grids can be called by name
+/// from the grids of all characters and items via the Call function.
+#[derive(Clone, Debug, Default)]
+pub struct CodeLibrary {
+    /// The compiled packages keyed by the bundle name.
+    packages: FxHashMap<String, TheCodePackage>,
+
+    /// The bundle names keyed by the bundle id, bundles can be renamed.
+    names: FxHashMap<Uuid, String>,
+}
+
+impl CodeLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts (or replaces) the compiled package of the given bundle.
+    pub fn insert(&mut self, name: String, package: TheCodePackage) {
+        self.remove(&package.id);
+        self.names.insert(package.id, name.clone());
+        self.packages.insert(name, package);
+    }
+
+    /// Removes the package of the given bundle.
+    pub fn remove(&mut self, id: &Uuid) {
+        if let Some(name) = self.names.remove(id) {
+            self.packages.remove(&name);
+        }
+    }
+
+    /// Returns the package of the given bundle.
+    pub fn get(&self, bundle: &str) -> Option<&TheCodePackage> {
+        self.packages.get(bundle)
+    }
+
+    /// Splits the given function name into the bundle and the module name. "Bundle" refers to
+    /// the main grid of the bundle, "Bundle.grid" to the given grid of the bundle.
+    pub fn split_function_name(name: &str) -> (&str, &str) {
+        name.split_once('.').unwrap_or((name, "main"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_bundles_replace_their_old_name() {
+        let package = TheCodePackage::new();
+        let id = package.id;
+
+        let mut library = CodeLibrary::new();
+        library.insert("Old".to_string(), package.clone());
+        library.insert("New".to_string(), package);
+        assert!(library.get("Old").is_none());
+        assert_eq!(library.get("New").map(|package| package.id), Some(id));
+
+        library.remove(&id);
+        assert!(library.get("New").is_none());
+    }
+
+    #[test]
+    fn function_names_default_to_the_main_grid() {
+        assert_eq!(CodeLibrary::split_function_name("Lib"), ("Lib", "main"));
+        assert_eq!(
+            CodeLibrary::split_function_name("Lib.grid"),
+            ("Lib", "grid")
+        );
+    }
+}
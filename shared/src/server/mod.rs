@@ -1,30 +1,40 @@
 use crate::prelude::*;
-use lazy_static::lazy_static;
-use std::sync::{mpsc, Mutex, RwLock};
+use rayon::prelude::*;
+use std::sync::Arc;
 use theframework::prelude::*;
 
+pub mod action;
+pub mod combat;
 pub mod context;
+pub mod diagnostics;
+pub mod event;
 pub mod functions;
+pub mod inventory;
+pub mod library;
+pub mod region_context;
 pub mod region_instance;
+pub mod snapshot;
+pub mod timer;
 pub mod update;
+pub mod vision;
 pub mod world;
 
 pub mod prelude {
+    pub use super::action::{InputEvent, PlayerAction, PlayerDirection};
     pub use super::context::ServerContext;
-    pub use super::region_instance::RegionInstance;
-    pub use super::update::{CharacterUpdate, RegionUpdate};
-    pub use super::world::World;
+    pub use super::diagnostics::CompileDiagnostic;
+    pub use super::event::CharacterEvent;
+    pub use super::inventory::Inventory;
+    pub use super::library::{CodeLibrary, MAX_CALL_DEPTH};
+    pub use super::region_context::{with_context, RegionContext, PATH_RETRY_TICKS};
+    pub use super::region_instance::{CharacterTransfer, RegionInstance};
+    pub use super::snapshot::{RegionSnapshot, ServerSnapshot};
+    pub use super::timer::Timer;
+    pub use super::update::{CharacterEffect, CharacterUpdate, ItemUpdate, RegionUpdate};
+    pub use super::world::{DayPhase, World, WorldDate};
     pub use super::Server;
 }
 
-lazy_static! {
-    pub static ref REGIONS: RwLock<FxHashMap<Uuid, Region>> = RwLock::new(FxHashMap::default());
-    pub static ref RNG: Mutex<rand::rngs::StdRng> = Mutex::new(rand::rngs::StdRng::from_entropy());
-    pub static ref TILES: RwLock<FxHashMap<Uuid, TheRGBATile>> = RwLock::new(FxHashMap::default());
-    pub static ref UPDATES: RwLock<FxHashMap<Uuid, RegionUpdate>> =
-        RwLock::new(FxHashMap::default());
-}
-
 use prelude::*;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -44,13 +54,43 @@ pub struct Server {
 
     instances: FxHashMap<Uuid, RegionInstance>,
 
+    /// The tiles of the project, shared with all region instances.
+    #[serde(skip)]
+    tiles: Arc<FxHashMap<Uuid, TheRGBATile>>,
+
+    /// The flags of the tiles of the project, shared with all region instances.
+    #[serde(skip)]
+    tile_flags: Arc<FxHashMap<Uuid, TileFlags>>,
+
     #[serde(skip)]
     characters: FxHashMap<Uuid, TheCodePackage>,
 
+    #[serde(skip)]
+    items: FxHashMap<Uuid, TheCodePackage>,
+
+    /// The compiled code bundles of the project, shared with all region instances.
+    #[serde(skip)]
+    library: Arc<CodeLibrary>,
+
     pub debug_mode: bool,
     pub world: World,
 
     pub anim_counter: usize,
+
+    /// The seed of the simulation. Each region derives its random number generator from it.
+    seed: u64,
+
+    /// All player actions received since the project was set.
+    #[serde(skip)]
+    input_log: Vec<InputEvent>,
+
+    /// The compile errors of the character, item and code bundles.
+    #[serde(skip)]
+    diagnostics: Vec<CompileDiagnostic>,
+
+    /// The runtime errors of the server, i.e. failed transfers between regions.
+    #[serde(skip)]
+    errors: Vec<String>,
 }
 
 impl Default for Server {
@@ -72,15 +112,37 @@ impl Server {
 
             instances: FxHashMap::default(),
 
+            tiles: Arc::new(FxHashMap::default()),
+            tile_flags: Arc::new(FxHashMap::default()),
+
             characters: FxHashMap::default(),
+            items: FxHashMap::default(),
+            library: Arc::new(CodeLibrary::default()),
 
             debug_mode: false,
             world: World::default(),
 
             anim_counter: 0,
+
+            seed: rand::random(),
+
+            input_log: vec![],
+
+            diagnostics: vec![],
+            errors: vec![],
         }
     }
 
+    /// Returns the seed of the simulation.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Sets the seed of the simulation. Takes effect on the next call to set_project.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
     /// Returns a mutable reference to the compiler.
     pub fn compiler(&mut self) -> &mut TheCompiler {
         &mut self.compiler
@@ -88,21 +150,18 @@ impl Server {
 
     /// Sets the current project. Resets the server.
     pub fn set_project(&mut self, project: Project) {
-        let mut regions = FxHashMap::default();
-        let mut updates = FxHashMap::default();
-        for region in &project.regions {
-            regions.insert(region.id, region.clone());
-            updates.insert(region.id, RegionUpdate::default());
-        }
-
+        self.diagnostics.clear();
+        self.errors.clear();
         self.characters = FxHashMap::default();
-
-        *REGIONS.write().unwrap() = regions;
-        *UPDATES.write().unwrap() = updates;
-        *TILES.write().unwrap() = project.extract_tiles();
+        self.items = FxHashMap::default();
+        self.tiles = Arc::new(project.extract_tiles());
+        self.tile_flags = Arc::new(project.extract_tile_flags());
+        self.compile_library(&project);
 
         self.world.reset();
+        self.world.ticks_per_minute = project.ticks_per_minute;
         self.anim_counter = 0;
+        self.input_log.clear();
 
         self.setup_regions(&project);
         self.project = project;
@@ -149,7 +208,14 @@ impl Server {
             let mut instance = RegionInstance::new();
 
             instance.set_debug_mode(self.debug_mode);
-            instance.setup(uuid, project);
+            instance.setup(
+                region.clone(),
+                self.tiles.clone(),
+                self.tile_flags.clone(),
+                project,
+                self.seed,
+            );
+            instance.set_library(self.library.clone());
 
             self.instances.insert(uuid, instance);
         }
@@ -159,14 +225,100 @@ impl Server {
             self.insert_character(bundle.clone());
         }
 
-        // Second pass we just create the region character instances.
+        // Add all items
+        for bundle in project.items.values() {
+            self.insert_item(bundle.clone());
+        }
+
+        // Second pass we just create the region character and item instances.
         for region in &project.regions {
             for character in region.characters.values() {
                 self.add_character_instance_to_region(region.id, character.clone());
             }
+            for item in region.items.values() {
+                self.add_item_instance_to_region(region.id, item.clone());
+            }
+        }
+    }
+
+    /// Takes a snapshot of the complete runtime state of the server.
+    pub fn snapshot(&self) -> ServerSnapshot {
+        let mut regions = vec![];
+
+        // Keep the project order so that snapshots of the same state are identical.
+        for region in &self.project.regions {
+            if let Some(instance) = self.instances.get(&region.id) {
+                regions.push(instance.snapshot());
+            }
+        }
+
+        ServerSnapshot {
+            seed: self.seed,
+            world: self.world.clone(),
+            anim_counter: self.anim_counter,
+            regions,
+            input_log: self.input_log.clone(),
         }
     }
 
+    /// Rebuilds the runtime state of the server from the given snapshot. The snapshot has to be
+    /// taken from a server running the current project.
+    pub fn restore_snapshot(&mut self, snapshot: ServerSnapshot) {
+        let project = self.project.clone();
+
+        // The project was compiled before, keep its compile errors instead of reporting them
+        // a second time.
+        let diagnostics = self.take_diagnostics();
+
+        self.seed = snapshot.seed;
+        self.characters = FxHashMap::default();
+        self.items = FxHashMap::default();
+        self.instances = FxHashMap::default();
+        self.tiles = Arc::new(project.extract_tiles());
+        self.tile_flags = Arc::new(project.extract_tile_flags());
+        self.compile_library(&project);
+
+        for region in &project.regions {
+            let mut instance = RegionInstance::new();
+
+            instance.set_debug_mode(self.debug_mode);
+            instance.setup(
+                region.clone(),
+                self.tiles.clone(),
+                self.tile_flags.clone(),
+                &project,
+                self.seed,
+            );
+            instance.set_library(self.library.clone());
+
+            self.instances.insert(region.id, instance);
+        }
+
+        for bundle in project.characters.values() {
+            self.insert_character(bundle.clone());
+        }
+
+        for bundle in project.items.values() {
+            self.insert_item(bundle.clone());
+        }
+
+        // Restore the regions, this also restores their current region data and updates.
+        for region in snapshot.regions {
+            if let Some(instance) = self.instances.get_mut(&region.id) {
+                instance.restore(region);
+            }
+        }
+
+        for instance in self.instances.values_mut() {
+            instance.take_diagnostics();
+        }
+        self.diagnostics = diagnostics;
+
+        self.world = snapshot.world;
+        self.anim_counter = snapshot.anim_counter;
+        self.input_log = snapshot.input_log;
+    }
+
     /// Starts the server.
     pub fn start(&mut self) {
         self.state = ServerState::Running;
@@ -182,41 +334,147 @@ impl Server {
         self.world.tick();
         self.anim_counter = self.anim_counter.wrapping_add(1);
 
-        let (sender, receiver) = mpsc::channel();
-        let mut join_handles = vec![];
+        // The regions are scheduled across the persistent rayon worker threads. Region
+        // instances only share state through their own context, so they can run in any order.
+        let world = &self.world;
+        self.instances
+            .par_iter_mut()
+            .for_each(|(_, instance)| instance.tick(world));
 
-        for (key, mut instance) in self.instances.drain() {
-            let sender = sender.clone();
-            let handle = std::thread::spawn(move || {
-                instance.tick();
-                sender.send((key, instance)).unwrap();
+        self.process_transfers();
+    }
+
+    /// Moves the character instances which requested a transfer during the last tick into
+    /// their new regions.
+    fn process_transfers(&mut self) {
+        // Keep the project order so that the transfers are deterministic.
+        let mut transfers = vec![];
+        for region in &self.project.regions {
+            if let Some(instance) = self.instances.get_mut(&region.id) {
+                for (character, region_name, position) in instance.take_transfers() {
+                    transfers.push((region.id, character, region_name, position));
+                }
+            }
+        }
+
+        for (from, character, region_name, position) in transfers {
+            let to = self.project.regions.iter().map(|r| r.id).find(|id| {
+                self.instances
+                    .get(id)
+                    .map_or(false, |instance| instance.region().name == region_name)
             });
 
-            join_handles.push(handle);
+            // Failed transfers keep the character where it is.
+            if let Some(to) = to {
+                if !self.transfer_character(from, to, character, position) {
+                    self.errors.push(format!(
+                        "Transfer into {} at ({}, {}) failed, the position is blocked",
+                        region_name, position.x, position.y
+                    ));
+                }
+            } else {
+                self.errors
+                    .push(format!("Transfer into unknown region {}", region_name));
+            }
         }
+    }
 
-        for handle in join_handles {
-            handle.join().unwrap();
+    /// Transfers the character instance from one region to the given position in another
+    /// region. Returns true on success, if the position is blocked or occupied the character
+    /// stays in its region.
+    pub fn transfer_character(
+        &mut self,
+        from: Uuid,
+        to: Uuid,
+        character: Uuid,
+        position: Vec2f,
+    ) -> bool {
+        if !self.instances.contains_key(&to) {
+            return false;
         }
 
-        drop(sender);
-        for (key, instance) in receiver {
-            self.instances.insert(key, instance);
+        let transfer = self
+            .instances
+            .get_mut(&from)
+            .and_then(|instance| instance.take_character_instance(character));
+
+        let Some(transfer) = transfer else {
+            return false;
+        };
+
+        let rejected = match self.instances.get_mut(&to) {
+            Some(instance) => instance.transfer_character_into(transfer, position),
+            None => Some(transfer),
+        };
+
+        if let Some(transfer) = rejected {
+            if let Some(instance) = self.instances.get_mut(&from) {
+                instance.restore_character_instance(transfer);
+            }
+            return false;
+        }
+
+        true
+    }
+
+    /// Ticks the server until the given condition is met or max_ticks have been executed.
+    /// The condition is checked after every tick. Returns true if the condition was met.
+    pub fn tick_until<F>(&mut self, max_ticks: i64, mut condition: F) -> bool
+    where
+        F: FnMut(&Server) -> bool,
+    {
+        for _ in 0..max_ticks {
+            self.tick();
+            if condition(self) {
+                return true;
+            }
         }
+        false
     }
 
-    /// Updates the tiles in the server. Called after live tilemap updates from the editor.
-    pub fn update_tiles(&mut self, tiles: FxHashMap<Uuid, TheRGBATile>) {
-        *TILES.write().unwrap() = tiles;
+    /// Returns the current update of the given region.
+    pub fn get_region_update(&self, region: &Uuid) -> Option<RegionUpdate> {
+        self.instances
+            .get(region)
+            .map(|instance| instance.update().clone())
+    }
+
+    /// Returns the current updates of all regions.
+    pub fn get_region_updates(&self) -> FxHashMap<Uuid, RegionUpdate> {
+        self.instances
+            .iter()
+            .map(|(id, instance)| (*id, instance.update().clone()))
+            .collect()
+    }
+
+    /// Updates the tiles and tile flags in the server. Called after live tilemap updates from
+    /// the editor.
+    pub fn update_tiles(
+        &mut self,
+        tiles: FxHashMap<Uuid, TheRGBATile>,
+        tile_flags: FxHashMap<Uuid, TileFlags>,
+    ) {
+        self.tiles = Arc::new(tiles);
+        self.tile_flags = Arc::new(tile_flags);
+        for instance in self.instances.values_mut() {
+            instance.set_tiles(self.tiles.clone(), self.tile_flags.clone());
+        }
     }
 
     /// Update the region instance for the region. Called after live updates from the editor.
     pub fn update_region(&mut self, region: &Region) {
-        if let Ok(r) = &mut REGIONS.write() {
-            r.insert(region.id, region.clone());
+        if let Some(instance) = self.instances.get_mut(&region.id) {
+            instance.set_region(region.clone());
         }
     }
 
+    /// Returns the current light map of the given region instance.
+    pub fn light_map(&self, uuid: &Uuid) -> Option<LightMap> {
+        self.instances
+            .get(uuid)
+            .map(|instance| instance.light_map().clone())
+    }
+
     /// Draws the given region instance into the given buffer. This drawing routine is only used by the editor.
     pub fn draw_region(
         &mut self,
@@ -237,19 +495,19 @@ impl Server {
         package.id = character.id;
 
         for grid in character.grids.values_mut() {
-            let rc = self.compiler.compile(grid);
-            if let Ok(mut module) = rc {
-                module.name = grid.name.clone();
-                println!(
-                    "RegionInstance::add_character: Compiled grid module: {}",
-                    grid.name
-                );
-                package.insert_module(module.name.clone(), module);
-            } else {
-                println!(
-                    "RegionInstance::add_character: Failed to compile grid: {}",
-                    grid.name
-                );
+            match self.compiler.compile(grid) {
+                Ok(mut module) => {
+                    module.name = grid.name.clone();
+                    package.insert_module(module.name.clone(), module);
+                }
+                Err(err) => {
+                    self.diagnostics.push(CompileDiagnostic::new(
+                        character.id,
+                        character.name.clone(),
+                        grid,
+                        &err,
+                    ));
+                }
             }
         }
 
@@ -260,6 +518,90 @@ impl Server {
         self.characters.insert(package.id, package);
     }
 
+    /// Compiles the code bundles of the project into the library.
+    fn compile_library(&mut self, project: &Project) {
+        let mut library = CodeLibrary::new();
+        for bundle in project.codes.values() {
+            let package = self.compile_code(bundle.clone());
+            library.insert(bundle.name.clone(), package);
+        }
+        self.library = Arc::new(library);
+    }
+
+    /// Compiles a code bundle into a package.
+    fn compile_code(&mut self, mut code: TheCodeBundle) -> TheCodePackage {
+        let mut package = TheCodePackage::new();
+        package.id = code.id;
+
+        for grid in code.grids.values_mut() {
+            match self.compiler.compile(grid) {
+                Ok(mut module) => {
+                    module.name = grid.name.clone();
+                    package.insert_module(module.name.clone(), module);
+                }
+                Err(err) => {
+                    self.diagnostics.push(CompileDiagnostic::new(
+                        code.id,
+                        code.name.clone(),
+                        grid,
+                        &err,
+                    ));
+                }
+            }
+        }
+
+        package
+    }
+
+    /// Add a new or changed code bundle (TheCodeBundle) to the library of the server.
+    pub fn insert_code(&mut self, code: TheCodeBundle) {
+        let name = code.name.clone();
+        let package = self.compile_code(code);
+        Arc::make_mut(&mut self.library).insert(name, package);
+
+        for instance in self.instances.values_mut() {
+            instance.set_library(self.library.clone());
+        }
+    }
+
+    /// Remove a code bundle from the library of the server.
+    pub fn remove_code(&mut self, id: &Uuid) {
+        Arc::make_mut(&mut self.library).remove(id);
+
+        for instance in self.instances.values_mut() {
+            instance.set_library(self.library.clone());
+        }
+    }
+
+    /// Add a new item (TheCodeBundle) to the server.
+    pub fn insert_item(&mut self, mut item: TheCodeBundle) {
+        let mut package = TheCodePackage::new();
+        package.id = item.id;
+
+        for grid in item.grids.values_mut() {
+            match self.compiler.compile(grid) {
+                Ok(mut module) => {
+                    module.name = grid.name.clone();
+                    package.insert_module(module.name.clone(), module);
+                }
+                Err(err) => {
+                    self.diagnostics.push(CompileDiagnostic::new(
+                        item.id,
+                        item.name.clone(),
+                        grid,
+                        &err,
+                    ));
+                }
+            }
+        }
+
+        for instance in self.instances.values_mut() {
+            instance.insert_item(package.clone());
+        }
+
+        self.items.insert(package.id, package);
+    }
+
     /// Get the debug module for the given module id.
     pub fn get_region_debug_module(&mut self, region: Uuid, module_id: Uuid) -> TheDebugModule {
         if let Some(instance) = self.instances.get_mut(&region) {
@@ -303,6 +645,81 @@ impl Server {
         }
     }
 
+    /// Adds a new player controlled character instance to the given region at the given
+    /// position. Returns the module id of the instance (for debugging).
+    pub fn add_player_instance_to_region(
+        &mut self,
+        region: Uuid,
+        character: Character,
+        position: Vec2f,
+    ) -> Option<Uuid> {
+        let id = character.id;
+        if let Some(instance) = self.instances.get_mut(&region) {
+            let module_id = instance.add_character_instance(character);
+            instance.set_character_position(id, position);
+            instance.set_player(id, true);
+            module_id
+        } else {
+            None
+        }
+    }
+
+    /// Sets if the given character instance is controlled by a player.
+    pub fn set_player(&mut self, character: Uuid, player: bool) {
+        for instance in self.instances.values_mut() {
+            if instance.has_character_instance(&character) {
+                instance.set_player(character, player);
+            }
+        }
+    }
+
+    /// Passes an action to a player controlled character instance. The action is executed
+    /// during the next tick and recorded in the input log. Returns false if the character
+    /// instance is not a player.
+    pub fn player_action(&mut self, character: Uuid, action: PlayerAction) -> bool {
+        for instance in self.instances.values_mut() {
+            if instance.is_player(&character) {
+                self.input_log.push(InputEvent {
+                    tick: self.world.tick_counter,
+                    character,
+                    action: action.clone(),
+                });
+                instance.queue_action(character, action);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns and clears the compile errors collected since the last call, including the ones
+    /// of the character and item instances in the regions.
+    pub fn take_diagnostics(&mut self) -> Vec<CompileDiagnostic> {
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        for region in &self.project.regions {
+            if let Some(instance) = self.instances.get_mut(&region.id) {
+                diagnostics.append(&mut instance.take_diagnostics());
+            }
+        }
+        diagnostics
+    }
+
+    /// Returns and clears the runtime errors collected since the last call, including the ones
+    /// of the regions.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        let mut errors = std::mem::take(&mut self.errors);
+        for region in &self.project.regions {
+            if let Some(instance) = self.instances.get_mut(&region.id) {
+                errors.append(&mut instance.take_errors());
+            }
+        }
+        errors
+    }
+
+    /// Returns the player actions received since the project was set.
+    pub fn input_log(&self) -> &Vec<InputEvent> {
+        &self.input_log
+    }
+
     /// Remove the character instance from the given region.
     pub fn remove_character_instance(&mut self, region: Uuid, character: Uuid) {
         if let Some(instance) = self.instances.get_mut(&region) {
@@ -345,4 +762,246 @@ impl Server {
             None
         }
     }
+
+    /// Adds a new item instance to the given region and returns its module id (for debugging).
+    pub fn add_item_instance_to_region(&mut self, region: Uuid, item: Item) -> Option<Uuid> {
+        if let Some(instance) = self.instances.get_mut(&region) {
+            instance.add_item_instance(item)
+        } else {
+            None
+        }
+    }
+
+    /// Remove the item instance from the given region.
+    pub fn remove_item_instance(&mut self, region: Uuid, item: Uuid) {
+        if let Some(instance) = self.instances.get_mut(&region) {
+            instance.remove_item_instance(item);
+        }
+    }
+
+    /// Returns the item instance id and the item id for the item at the given position for the given region.
+    pub fn get_item_at(&self, region: Uuid, pos: Vec2i) -> Option<(Uuid, Uuid)> {
+        if let Some(instance) = self.instances.get(&region) {
+            instance.get_item_at(pos)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the property of the item instance for the given region.
+    pub fn get_item_property(
+        &self,
+        region: Uuid,
+        item: Uuid,
+        property: String,
+    ) -> Option<TheValue> {
+        if let Some(instance) = self.instances.get(&region) {
+            instance.get_item_property(item, property)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the inventory of the given character instance.
+    pub fn get_inventory(&self, character: &Uuid) -> Option<Inventory> {
+        self.instances
+            .values()
+            .find_map(|instance| instance.get_inventory(character))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::tests::character_at;
+
+    /// Returns a character instance of the template at the given position.
+    fn instance(template: Uuid, x: i32, y: i32) -> Character {
+        let mut character = character_at(x, y);
+        character.character_id = template;
+        character
+    }
+
+    /// Returns a project with a region, a character instance controlled by the player and two
+    /// character instances walking around randomly, along with the id of the player.
+    fn project() -> (Project, Uuid) {
+        let mut project = Project::new();
+
+        let player = TheCodeBundle::new();
+        let player_template = player.id;
+        project.characters.insert(player.id, player);
+
+        // The main function of the walkers only calls RandWalk, which draws from the random
+        // number generator of the region on every tick.
+        let mut walker = TheCodeBundle::new();
+        let mut main = TheCodeGrid {
+            name: "main".into(),
+            ..Default::default()
+        };
+        main.insert_atom(
+            (0, 0),
+            TheCodeAtom::ExternalCall("RandWalk".to_string(), String::new(), vec![], vec![], None),
+        );
+        walker.insert_grid(main);
+        let walker_template = walker.id;
+        project.characters.insert(walker.id, walker);
+
+        let mut region = Region::new();
+        region.width = 10;
+        region.height = 10;
+
+        let player = instance(player_template, 2, 2);
+        let player_id = player.id;
+        region.characters.insert(player.id, player);
+        for (x, y) in [(6, 6), (8, 3)] {
+            let walker = instance(walker_template, x, y);
+            region.characters.insert(walker.id, walker);
+        }
+
+        project.regions.push(region);
+        (project, player_id)
+    }
+
+    /// Returns a server running the project with the given seed.
+    fn server(project: &Project, player: Uuid, seed: u64) -> Server {
+        let mut server = Server::new();
+        server.set_seed(seed);
+        server.set_project(project.clone());
+        server.set_player(player, true);
+        server.start();
+        server
+    }
+
+    /// Ticks the server, moving the player around. Returns the positions of all characters,
+    /// sorted by their id, after each tick.
+    fn run(server: &mut Server, player: Uuid, ticks: usize) -> Vec<Vec<(Uuid, Vec2f)>> {
+        let directions = [
+            PlayerDirection::East,
+            PlayerDirection::South,
+            PlayerDirection::West,
+            PlayerDirection::None,
+        ];
+
+        let mut positions = vec![];
+        for tick in 0..ticks {
+            let direction = directions[tick % directions.len()];
+            if direction != PlayerDirection::None {
+                server.player_action(player, PlayerAction::new("move", direction));
+            }
+            server.tick();
+
+            let mut characters: Vec<(Uuid, Vec2f)> = server
+                .get_region_updates()
+                .values()
+                .flat_map(|update| update.characters.iter())
+                .map(|(id, character)| (*id, character.position))
+                .collect();
+            characters.sort_by_key(|(id, _)| *id);
+            positions.push(characters);
+        }
+        positions
+    }
+
+    /// Returns the snapshot of the server as json value, which compares maps independent of
+    /// their order.
+    fn state(server: &Server) -> serde_json::Value {
+        serde_json::from_str(&server.snapshot().to_json()).unwrap()
+    }
+
+    #[test]
+    fn runs_with_the_same_seed_are_identical() {
+        let (project, player) = project();
+
+        let mut a = server(&project, player, 7);
+        let mut b = server(&project, player, 7);
+        let positions = run(&mut a, player, 40);
+        assert_eq!(positions, run(&mut b, player, 40));
+        assert_eq!(a.input_log(), b.input_log());
+
+        // The walkers used the random number generator to move.
+        let walked = positions.windows(2).any(|ticks| {
+            ticks[0]
+                .iter()
+                .zip(&ticks[1])
+                .any(|((id, from), (_, to))| *id != player && from != to)
+        });
+        assert!(walked);
+
+        let mut c = server(&project, player, 8);
+        assert_ne!(positions, run(&mut c, player, 40));
+    }
+
+    #[test]
+    fn restored_snapshot_continues_like_the_original() {
+        let (project, player) = project();
+
+        let mut a = server(&project, player, 7);
+        run(&mut a, player, 10);
+
+        let json = a.snapshot().to_json();
+        let snapshot = ServerSnapshot::from_json(&json).unwrap();
+
+        let mut b = Server::new();
+        b.set_project(project.clone());
+        b.restore_snapshot(snapshot);
+        b.start();
+
+        assert_eq!(b.seed(), 7);
+        assert_eq!(b.world.tick_counter, 10);
+        assert_eq!(state(&a), state(&b));
+        assert!(!b.input_log().is_empty());
+        assert_eq!(a.input_log(), b.input_log());
+
+        assert_eq!(run(&mut a, player, 10), run(&mut b, player, 10));
+        assert_eq!(state(&a), state(&b));
+
+        assert!(ServerSnapshot::from_json("{}").is_none());
+    }
+
+    #[test]
+    fn blocked_transfers_keep_the_character_in_its_region() {
+        let mut project = Project::new();
+        let template = TheCodeBundle::new();
+        let template_id = template.id;
+        project.characters.insert(template.id, template);
+
+        let mut from = Region::new();
+        from.name = "From".into();
+        from.width = 10;
+        from.height = 10;
+        let traveller = instance(template_id, 1, 1);
+        let traveller_id = traveller.id;
+        from.characters.insert(traveller.id, traveller);
+
+        let mut to = Region::new();
+        to.name = "To".into();
+        to.width = 10;
+        to.height = 10;
+        let guard = instance(template_id, 3, 3);
+        to.characters.insert(guard.id, guard);
+
+        let (from_id, to_id) = (from.id, to.id);
+        project.regions.push(from);
+        project.regions.push(to);
+
+        let mut server = Server::new();
+        server.set_project(project);
+        server.start();
+
+        let at = |server: &Server, region: Uuid, x: i32, y: i32| {
+            server
+                .get_character_at(region, vec2i(x, y))
+                .map(|(id, _)| id)
+        };
+
+        // Occupied and outside of the region.
+        assert!(!server.transfer_character(from_id, to_id, traveller_id, vec2f(3.0, 3.0)));
+        assert!(!server.transfer_character(from_id, to_id, traveller_id, vec2f(20.0, 3.0)));
+        assert_eq!(at(&server, from_id, 1, 1), Some(traveller_id));
+
+        assert!(server.transfer_character(from_id, to_id, traveller_id, vec2f(4.0, 4.0)));
+        assert_eq!(at(&server, to_id, 4, 4), Some(traveller_id));
+        assert_eq!(at(&server, from_id, 1, 1), None);
+    }
 }
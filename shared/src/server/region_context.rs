@@ -0,0 +1,335 @@
+use crate::prelude::*;
+use crate::server::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::cell::RefCell;
+use std::sync::Arc;
+use theframework::prelude::*;
+
+/// The number of ticks a character without a path to its target waits before searching again.
+pub const PATH_RETRY_TICKS: i64 = 10;
+
+thread_local! {
+    /// The context of the region instance which is currently executing code on this thread.
+    /// External calls are plain function pointers and cannot capture the server state, so the
+    /// executing region instance swaps its context in and out around code execution.
+    static CONTEXT: RefCell<RegionContext> = RefCell::new(RegionContext::default());
+}
+
+/// Calls the given closure with the context of the region instance currently executing code on
+/// this thread. Must not be nested, release the context before executing sub functions.
+pub fn with_context<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut RegionContext) -> R,
+{
+    CONTEXT.with(|context| f(&mut context.borrow_mut()))
+}
+
+/// The runtime state of a region instance which is shared with the external compiler calls.
+#[derive(Clone, Debug)]
+pub struct RegionContext {
+    /// The region as currently used by the server.
+    pub region: Region,
+
+    /// The current update of the region, read by the clients.
+    pub update: RegionUpdate,
+
+    /// The tiles of the project, shared by all regions of a server.
+    pub tiles: Arc<FxHashMap<Uuid, TheRGBATile>>,
+
+    /// The flags of the tiles of the project, shared by all regions of a server.
+    pub tile_flags: Arc<FxHashMap<Uuid, TileFlags>>,
+
+    /// The random number generator of the region, derived from the server seed.
+    pub rng: ChaCha8Rng,
+
+    /// Character instances which requested a transfer to another region during the tick as
+    /// (character instance id, region name, position) tuples. Processed by the server.
+    pub transfers: Vec<(Uuid, String, Vec2f)>,
+
+    /// The cached paths of the characters walking towards a target, keyed by the character
+    /// instance id. Contains the target and the remaining path including the current position.
+    pub paths: FxHashMap<Uuid, (Vec2i, Vec<Vec2i>)>,
+
+    /// The characters which found no path to their target along with the target and the world
+    /// tick at which they search again.
+    pub unreachable: FxHashMap<Uuid, (Vec2i, i64)>,
+
+    /// The character instances standing on each tile.
+    pub occupancy: FxHashMap<(i32, i32), Vec<Uuid>>,
+
+    /// Character instances other characters can walk through.
+    pub passable: FxHashSet<Uuid>,
+
+    /// Character instances which get pushed aside by other characters.
+    pub pushable: FxHashSet<Uuid>,
+
+    /// Character instances which can enter water tiles.
+    pub swimmers: FxHashSet<Uuid>,
+
+    /// Character instances crossing slow terrain along with the world tick at which they can
+    /// move again.
+    pub move_delays: FxHashMap<Uuid, i64>,
+
+    /// Character instances pushed to a new position during code execution. Their sandbox
+    /// objects are updated by the region instance after the execution.
+    pub pushed: Vec<(Uuid, Vec2f)>,
+
+    /// The inventories of the characters, keyed by the character instance id.
+    pub inventories: FxHashMap<Uuid, Inventory>,
+
+    /// Item functions to execute after the current code execution as (item instance id,
+    /// function name, character instance id) tuples.
+    pub item_events: Vec<(Uuid, String, Uuid)>,
+
+    /// The compiled code bundles of the project, shared by all regions of a server.
+    pub library: Arc<CodeLibrary>,
+
+    /// The copies of the library packages used by this region, keyed by the bundle name.
+    /// Created on the first call, a package is taken out while it executes.
+    pub functions: FxHashMap<String, TheCodePackage>,
+
+    /// The nesting depth of the currently executing Call functions.
+    pub call_depth: usize,
+
+    /// The runtime errors of the code executed during the tick.
+    pub errors: Vec<String>,
+
+    /// The events sent during the tick, delivered by the region instance in the next tick.
+    pub events: Vec<CharacterEvent>,
+
+    /// The state of the world during the current tick.
+    pub world: World,
+
+    /// The running timers of the characters in the order they were started.
+    pub timers: Vec<Timer>,
+
+    /// Character instances whose hit points dropped to zero during code execution.
+    pub deaths: Vec<Uuid>,
+
+    /// The dead character instances along with the world tick at which they respawn.
+    pub dead: FxHashMap<Uuid, Option<i64>>,
+}
+
+impl Default for RegionContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegionContext {
+    pub fn new() -> Self {
+        Self {
+            region: Region::default(),
+            update: RegionUpdate::default(),
+            tiles: Arc::new(FxHashMap::default()),
+            tile_flags: Arc::new(FxHashMap::default()),
+            rng: ChaCha8Rng::seed_from_u64(0),
+            transfers: vec![],
+            paths: FxHashMap::default(),
+            unreachable: FxHashMap::default(),
+            occupancy: FxHashMap::default(),
+            passable: FxHashSet::default(),
+            pushable: FxHashSet::default(),
+            swimmers: FxHashSet::default(),
+            move_delays: FxHashMap::default(),
+            pushed: vec![],
+            inventories: FxHashMap::default(),
+            item_events: vec![],
+            library: Arc::new(CodeLibrary::default()),
+            functions: FxHashMap::default(),
+            call_depth: 0,
+            errors: vec![],
+            events: vec![],
+            world: World::default(),
+            timers: vec![],
+            deaths: vec![],
+            dead: FxHashMap::default(),
+        }
+    }
+
+    /// Returns the first character instance (except the given one) blocking the given tile.
+    /// Dead characters do not block.
+    pub fn occupant_at(&self, pos: Vec2i, except: Uuid) -> Option<Uuid> {
+        self.occupancy.get(&(pos.x, pos.y)).and_then(|ids| {
+            ids.iter()
+                .find(|id| {
+                    **id != except && !self.passable.contains(id) && !self.dead.contains_key(id)
+                })
+                .copied()
+        })
+    }
+
+    /// Returns true if the tiles at the given position let the character enter, i.e. they are
+    /// not blocking and water tiles are only entered by swimmers.
+    pub fn can_enter(&self, character: Uuid, pos: Vec2f) -> bool {
+        self.can_enter_tile(pos, self.swimmers.contains(&character))
+    }
+
+    /// Returns true if the tiles at the given position can be entered, by a swimmer or not.
+    pub fn can_enter_tile(&self, pos: Vec2f, swims: bool) -> bool {
+        self.region
+            .can_move_to(vec3f(pos.x, pos.y, 0.0), &self.tiles)
+            && (swims
+                || !self
+                    .region
+                    .flags_at(vec2i(pos.x as i32, pos.y as i32), &self.tile_flags)
+                    .water)
+    }
+
+    /// Returns true if the character can move to the given position, i.e. the tiles can be
+    /// entered and no other character is standing there.
+    pub fn can_move_to(&self, character: Uuid, pos: Vec2f) -> bool {
+        self.can_enter(character, pos)
+            && self
+                .occupant_at(vec2i(pos.x as i32, pos.y as i32), character)
+                .is_none()
+    }
+
+    /// Moves the character by one step if possible. A pushable character standing on the
+    /// destination is pushed one tile further in the same direction. Characters which entered
+    /// slow terrain have to wait for the movement cost of the tile before moving again.
+    /// Returns true on success.
+    pub fn try_move(&mut self, character: Uuid, from: Vec2f, to: Vec2f) -> bool {
+        if let Some(tick) = self.move_delays.get(&character) {
+            if *tick > self.world.tick_counter {
+                return false;
+            }
+            self.move_delays.remove(&character);
+        }
+
+        if !self.can_enter(character, to) {
+            return false;
+        }
+
+        if let Some(occupant) = self.occupant_at(vec2i(to.x as i32, to.y as i32), character) {
+            let push_to = to + (to - from);
+            if !self.pushable.contains(&occupant) || !self.can_move_to(occupant, push_to) {
+                return false;
+            }
+            self.set_position(occupant, push_to, true);
+            self.pushed.push((occupant, push_to));
+        }
+
+        self.set_position(character, to, true);
+
+        let cost = self
+            .region
+            .flags_at(vec2i(to.x as i32, to.y as i32), &self.tile_flags)
+            .movement_cost;
+        if cost > 1 {
+            self.move_delays
+                .insert(character, self.world.tick_counter + cost as i64 - 1);
+        }
+        true
+    }
+
+    /// Sets the position of the character in the update and the occupancy. If animate is
+    /// true the client interpolates the movement.
+    pub fn set_position(&mut self, character: Uuid, position: Vec2f, animate: bool) {
+        if let Some(cu) = self.update.characters.get_mut(&character) {
+            let old_position = cu.position;
+            let old_key = (old_position.x as i32, old_position.y as i32);
+            if let Some(ids) = self.occupancy.get_mut(&old_key) {
+                ids.retain(|id| *id != character);
+                if ids.is_empty() {
+                    self.occupancy.remove(&old_key);
+                }
+            }
+
+            cu.position = position;
+            cu.moving = if animate {
+                Some((old_position, position))
+            } else {
+                None
+            };
+            cu.move_delta = 0.0;
+
+            self.occupancy
+                .entry((position.x as i32, position.y as i32))
+                .or_default()
+                .push(character);
+        }
+    }
+
+    /// Removes the character from the update and the occupancy.
+    pub fn remove_character(&mut self, character: Uuid) {
+        if let Some(cu) = self.update.characters.remove(&character) {
+            let key = (cu.position.x as i32, cu.position.y as i32);
+            if let Some(ids) = self.occupancy.get_mut(&key) {
+                ids.retain(|id| *id != character);
+                if ids.is_empty() {
+                    self.occupancy.remove(&key);
+                }
+            }
+        }
+        self.paths.remove(&character);
+        self.unreachable.remove(&character);
+        self.timers.retain(|timer| timer.character != character);
+        self.deaths.retain(|id| *id != character);
+        self.dead.remove(&character);
+        self.passable.remove(&character);
+        self.pushable.remove(&character);
+        self.swimmers.remove(&character);
+        self.move_delays.remove(&character);
+    }
+
+    /// Rebuilds the occupancy from the character positions in the update.
+    pub fn rebuild_occupancy(&mut self) {
+        self.occupancy.clear();
+
+        // Sort by id to keep the order of the occupants deterministic.
+        let mut characters: Vec<(&Uuid, &CharacterUpdate)> =
+            self.update.characters.iter().collect();
+        characters.sort_by_key(|(id, _)| **id);
+
+        for (id, cu) in characters {
+            self.occupancy
+                .entry((cu.position.x as i32, cu.position.y as i32))
+                .or_default()
+                .push(*id);
+        }
+    }
+
+    /// Finds the cheapest path for the character to the target, avoiding other characters and
+    /// slow terrain.
+    pub fn find_path(&self, character: Uuid, from: Vec2i, to: Vec2i) -> Option<Vec<Vec2i>> {
+        self.region.find_path(from, to, |pos| {
+            if self.can_move_to(character, vec2f(pos.x as f32, pos.y as f32)) {
+                Some(self.region.flags_at(pos, &self.tile_flags).movement_cost)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the item lying on the given tile with the given name. An empty name matches
+    /// any item.
+    pub fn item_at(&self, pos: Vec2i, name: &str) -> Option<Uuid> {
+        // Sort by id to keep the result deterministic.
+        let mut items: Vec<(&Uuid, &ItemUpdate)> = self
+            .update
+            .items
+            .iter()
+            .filter(|(_, item)| {
+                item.position.x as i32 == pos.x
+                    && item.position.y as i32 == pos.y
+                    && (name.is_empty() || item.name == name)
+            })
+            .collect();
+        items.sort_by_key(|(id, _)| **id);
+        items.first().map(|(id, _)| **id)
+    }
+
+    /// Removes the item from the ground and from all inventories.
+    pub fn remove_item(&mut self, item: Uuid) {
+        self.update.items.remove(&item);
+        for inventory in self.inventories.values_mut() {
+            inventory.remove(&item);
+        }
+    }
+
+    /// Swaps this context with the one of the current thread.
+    pub fn swap(&mut self) {
+        CONTEXT.with(|context| std::mem::swap(&mut *context.borrow_mut(), self));
+    }
+}
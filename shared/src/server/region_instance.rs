@@ -1,8 +1,29 @@
+use super::combat;
 use super::prelude::*;
+use super::vision;
+use crate::lighting;
 use crate::prelude::*;
-use crate::server::{REGIONS, UPDATES};
+use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
 use theframework::prelude::*;
 
+/// A character instance in transit between two region instances.
+#[derive(Clone, Debug)]
+pub struct CharacterTransfer {
+    pub character: Character,
+    pub object: TheCodeObject,
+    pub package: TheCodePackage,
+    pub update: Option<CharacterUpdate>,
+    pub player: bool,
+
+    /// The inventory of the character and the carried item instances.
+    pub inventory: Option<Inventory>,
+    pub items: Vec<(Item, TheCodeObject, TheCodePackage)>,
+
+    /// The running timers of the character.
+    pub timers: Vec<Timer>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RegionInstance {
     pub id: Uuid,
@@ -19,6 +40,52 @@ pub struct RegionInstance {
     #[serde(skip)]
     characters_ids: Vec<(Uuid, Uuid)>,
 
+    /// The source data of the character instances, needed to rebuild them from snapshots.
+    #[serde(skip)]
+    characters_data: FxHashMap<Uuid, Character>,
+
+    #[serde(skip)]
+    items: FxHashMap<Uuid, TheCodePackage>,
+
+    #[serde(skip)]
+    items_instances: FxHashMap<Uuid, TheCodePackage>,
+
+    /// For fast lookups an array of (item_instance_id, item_id) tuples.
+    #[serde(skip)]
+    items_ids: Vec<(Uuid, Uuid)>,
+
+    /// The source data of the item instances, needed to rebuild them from snapshots.
+    #[serde(skip)]
+    items_data: FxHashMap<Uuid, Item>,
+
+    /// The region, its update, the tiles and the random number generator of this instance.
+    #[serde(skip)]
+    context: RegionContext,
+
+    /// The character instances controlled by players. They do not run their main function.
+    #[serde(skip)]
+    players: Vec<Uuid>,
+
+    /// The player actions to execute during the next tick.
+    #[serde(skip)]
+    actions: Vec<(Uuid, PlayerAction)>,
+
+    /// The areas each character instance was inside of at the end of the last tick.
+    #[serde(skip)]
+    character_areas: FxHashMap<Uuid, Vec<Uuid>>,
+
+    /// The events sent during the last tick, delivered during the next tick.
+    #[serde(skip)]
+    events: Vec<CharacterEvent>,
+
+    /// The compile errors of the instance bundles.
+    #[serde(skip)]
+    diagnostics: Vec<CompileDiagnostic>,
+
+    /// The light map computed at the end of the last tick.
+    #[serde(skip)]
+    light_map: LightMap,
+
     redraw_ms: u32,
     tick_ms: u32,
 }
@@ -41,6 +108,23 @@ impl RegionInstance {
             characters: FxHashMap::default(),
             characters_instances: FxHashMap::default(),
             characters_ids: vec![],
+            characters_data: FxHashMap::default(),
+
+            items: FxHashMap::default(),
+            items_instances: FxHashMap::default(),
+            items_ids: vec![],
+            items_data: FxHashMap::default(),
+
+            context: RegionContext::new(),
+
+            players: vec![],
+            actions: vec![],
+            character_areas: FxHashMap::default(),
+            events: vec![],
+
+            diagnostics: vec![],
+
+            light_map: LightMap::default(),
 
             redraw_ms: 1000 / 30,
             tick_ms: 250,
@@ -48,19 +132,97 @@ impl RegionInstance {
     }
 
     /// Sets up the region instance.
-    pub fn setup(&mut self, id: Uuid, project: &Project) {
+    pub fn setup(
+        &mut self,
+        region: Region,
+        tiles: Arc<FxHashMap<Uuid, TheRGBATile>>,
+        tile_flags: Arc<FxHashMap<Uuid, TileFlags>>,
+        project: &Project,
+        seed: u64,
+    ) {
+        let id = region.id;
         self.id = id;
         self.sandbox.id = id;
 
+        self.context.region = region;
+        self.context.update = RegionUpdate::default();
+        self.context.tiles = tiles;
+        self.context.tile_flags = tile_flags;
+
+        // Every region gets its own deterministic generator so that the outcome of a region
+        // does not depend on the thread scheduling of the other regions.
+        let id = id.as_u128();
+        self.context.rng = ChaCha8Rng::seed_from_u64(seed ^ (id as u64) ^ ((id >> 64) as u64));
+
         self.tick_ms = project.tick_ms;
         self.redraw_ms = 1000 / project.target_fps;
     }
 
     /// Tick. Compute the next frame.
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self, world: &World) {
+        self.context.world = world.clone();
+
+        // The combat effects are only shown for one tick.
+        for cu in self.context.update.characters.values_mut() {
+            cu.effects.clear();
+        }
+
+        // Dead characters cannot act.
+        let actions: Vec<(Uuid, PlayerAction)> = std::mem::take(&mut self.actions)
+            .into_iter()
+            .filter(|(instance_id, _)| !self.context.dead.contains_key(instance_id))
+            .collect();
+        for (instance_id, action) in &actions {
+            if action.is_move() {
+                self.move_character(*instance_id, action.direction.to_offset());
+            }
+        }
+
+        self.context.swap();
+
+        // All player actions (including moves) are passed to the on_action function of the
+        // character template and of the instance.
+        for (instance_id, action) in actions {
+            let character_id = self
+                .characters_ids
+                .iter()
+                .find(|(id, _)| *id == instance_id)
+                .map(|(_, character_id)| *character_id);
+
+            if let Some(object) = self.sandbox.objects.get_mut(&instance_id) {
+                object.set("action".to_string(), TheValue::Text(action.action.clone()));
+                object.set(
+                    "direction".to_string(),
+                    TheValue::Text(action.direction.name()),
+                );
+            }
+
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), instance_id);
+
+            if let Some(character_id) = character_id {
+                if let Some(template) = self.characters.get_mut(&character_id) {
+                    template.execute("on_action".to_string(), &mut self.sandbox);
+                }
+            }
+            if let Some(instance) = self.characters_instances.get_mut(&instance_id) {
+                instance.execute("on_action".to_string(), &mut self.sandbox);
+            }
+        }
+
+        self.process_events();
+        self.process_timers();
+        self.process_respawns();
+
+        let dead = with_context(|context| context.dead.clone());
+
         // We iterate over all character instances and execute their main function
         // as well as the main function of their character template.
         for (instance_id, character_id) in &mut self.characters_ids {
+            if self.players.contains(instance_id) || dead.contains_key(instance_id) {
+                continue;
+            }
+
             self.sandbox.clear_runtime_states();
             self.sandbox
                 .aliases
@@ -80,6 +242,499 @@ impl RegionInstance {
                 // );
             }
         }
+
+        self.process_item_events();
+
+        self.context.swap();
+
+        self.process_areas();
+        self.process_tile_damage();
+        self.process_deaths();
+
+        self.apply_pushes();
+        self.update_character_flags();
+        self.update_lights();
+
+        self.events.append(&mut self.context.events);
+    }
+
+    /// Executes the functions of the expired timers on their characters. Repeating timers are
+    /// restarted. Must be called while the context is swapped in.
+    fn process_timers(&mut self) {
+        let expired = with_context(|context| {
+            let tick = context.world.tick_counter;
+
+            let mut expired = vec![];
+            context.timers.retain_mut(|timer| {
+                if timer.tick > tick {
+                    return true;
+                }
+                expired.push((timer.character, timer.function.clone()));
+                if let Some(interval) = timer.interval {
+                    timer.tick = tick + interval.max(1);
+                    true
+                } else {
+                    false
+                }
+            });
+            expired
+        });
+
+        for (instance_id, function) in expired {
+            let character_id = self
+                .characters_ids
+                .iter()
+                .find(|(id, _)| *id == instance_id)
+                .map(|(_, character_id)| *character_id);
+
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), instance_id);
+
+            if let Some(character_id) = character_id {
+                if let Some(template) = self.characters.get_mut(&character_id) {
+                    template.execute(function.clone(), &mut self.sandbox);
+                }
+            }
+            if let Some(instance) = self.characters_instances.get_mut(&instance_id) {
+                instance.execute(function, &mut self.sandbox);
+            }
+        }
+    }
+
+    /// Damages the living characters standing on damaging tiles.
+    fn process_tile_damage(&mut self) {
+        // Sort by id to keep the order of the deaths deterministic.
+        let mut damaged: Vec<(Uuid, i32)> = self
+            .context
+            .update
+            .characters
+            .iter()
+            .filter(|(_, cu)| !cu.dead)
+            .map(|(id, cu)| {
+                let pos = vec2i(cu.position.x as i32, cu.position.y as i32);
+                let flags = self.context.region.flags_at(pos, &self.context.tile_flags);
+                (*id, flags.damage)
+            })
+            .filter(|(_, damage)| *damage > 0)
+            .collect();
+        damaged.sort();
+
+        for (character, damage) in damaged {
+            if let Some(object) = self.sandbox.objects.get_mut(&character) {
+                let hp = combat::change_hit_points(object, -damage);
+                let (_, max_hp) = combat::hit_points(object);
+                let effect = CharacterEffect::Damage(damage);
+                combat::record_hit_points(&mut self.context, character, hp, max_hp, effect);
+            }
+        }
+    }
+
+    /// Marks the characters whose hit points dropped to zero as dead and executes their
+    /// on_death function. Characters with a "respawn_ticks" property respawn after the given
+    /// number of ticks.
+    fn process_deaths(&mut self) {
+        let deaths = std::mem::take(&mut self.context.deaths);
+        if deaths.is_empty() {
+            return;
+        }
+
+        for character in &deaths {
+            let respawn = self
+                .sandbox
+                .objects
+                .get(character)
+                .and_then(|o| o.get(&"respawn_ticks".into()))
+                .and_then(|v| v.to_i32())
+                .filter(|ticks| *ticks > 0)
+                .map(|ticks| self.context.world.tick_counter + ticks as i64);
+
+            self.context.dead.insert(*character, respawn);
+            self.context.paths.remove(character);
+            self.context.unreachable.remove(character);
+            if let Some(cu) = self.context.update.characters.get_mut(character) {
+                cu.dead = true;
+                cu.effects.push(CharacterEffect::Death);
+            }
+        }
+
+        self.context.swap();
+        for character in deaths {
+            self.execute_character_function(character, "on_death");
+        }
+        self.context.swap();
+    }
+
+    /// Respawns the dead characters whose respawn tick is reached with full hit points at their
+    /// spawn position and executes their on_respawn function. Must be called while the
+    /// context is swapped in.
+    fn process_respawns(&mut self) {
+        let respawns = with_context(|context| {
+            let tick = context.world.tick_counter;
+
+            let mut respawns: Vec<Uuid> = context
+                .dead
+                .iter()
+                .filter(|(_, respawn)| matches!(respawn, Some(respawn) if *respawn <= tick))
+                .map(|(id, _)| *id)
+                .collect();
+            respawns.sort();
+
+            for id in &respawns {
+                context.dead.remove(id);
+            }
+            respawns
+        });
+
+        for character in respawns {
+            let mut max_hp = combat::DEFAULT_MAX_HP;
+            let mut spawn = None;
+
+            if let Some(object) = self.sandbox.objects.get_mut(&character) {
+                max_hp = combat::hit_points(object).1;
+                object.set("hp".to_string(), TheValue::Int(max_hp));
+
+                if let Some(TheValue::Position(p)) = object.get(&"spawn".into()).cloned() {
+                    object.set("position".to_string(), TheValue::Position(p));
+                    spawn = Some(vec2f(p.x, p.y));
+                }
+            }
+
+            with_context(|context| {
+                if let Some(cu) = context.update.characters.get_mut(&character) {
+                    cu.dead = false;
+                    cu.hp = max_hp;
+                    cu.max_hp = max_hp;
+                    cu.effects.push(CharacterEffect::Respawn);
+                }
+                if let Some(spawn) = spawn {
+                    context.set_position(character, spawn, false);
+                }
+            });
+
+            self.execute_character_function(character, "on_respawn");
+        }
+    }
+
+    /// Executes the given function of the character template and of the character instance.
+    fn execute_character_function(&mut self, instance_id: Uuid, function: &str) {
+        let character_id = self
+            .characters_ids
+            .iter()
+            .find(|(id, _)| *id == instance_id)
+            .map(|(_, character_id)| *character_id);
+
+        self.sandbox.clear_runtime_states();
+        self.sandbox.aliases.insert("self".to_string(), instance_id);
+
+        if let Some(character_id) = character_id {
+            if let Some(template) = self.characters.get_mut(&character_id) {
+                template.execute(function.to_string(), &mut self.sandbox);
+            }
+        }
+        if let Some(instance) = self.characters_instances.get_mut(&instance_id) {
+            instance.execute(function.to_string(), &mut self.sandbox);
+        }
+    }
+
+    /// Delivers the events sent during the last tick by executing the on_<event> function of
+    /// the receiving characters. The value of the event is passed in the "event_value"
+    /// property and the name of the sender in the "event_sender" property of the receiver.
+    /// Dead characters do not receive events. Must be called while the context is swapped in.
+    fn process_events(&mut self) {
+        let dead = with_context(|context| context.dead.clone());
+
+        for event in std::mem::take(&mut self.events) {
+            let sender = if let Some(TheValue::Text(name)) = self
+                .sandbox
+                .objects
+                .get(&event.sender)
+                .and_then(|o| o.get(&"name".into()))
+            {
+                name.clone()
+            } else {
+                String::new()
+            };
+
+            let receivers: Vec<(Uuid, Uuid)> = self
+                .characters_ids
+                .iter()
+                .filter(|(instance_id, _)| {
+                    !dead.contains_key(instance_id)
+                        && matches!(
+                            self.sandbox
+                                .objects
+                                .get(instance_id)
+                                .and_then(|o| o.get(&"name".into())),
+                            Some(TheValue::Text(name)) if *name == event.target
+                        )
+                })
+                .copied()
+                .collect();
+
+            let function = format!("on_{}", event.name);
+
+            for (instance_id, character_id) in receivers {
+                if let Some(object) = self.sandbox.objects.get_mut(&instance_id) {
+                    object.set("event_value".to_string(), event.value.clone());
+                    object.set("event_sender".to_string(), TheValue::Text(sender.clone()));
+                }
+
+                self.sandbox.clear_runtime_states();
+                self.sandbox.aliases.insert("self".to_string(), instance_id);
+
+                if let Some(template) = self.characters.get_mut(&character_id) {
+                    template.execute(function.clone(), &mut self.sandbox);
+                }
+                if let Some(instance) = self.characters_instances.get_mut(&instance_id) {
+                    instance.execute(function.clone(), &mut self.sandbox);
+                }
+            }
+        }
+    }
+
+    /// Checks which areas the characters are in and executes their on_enter_area,
+    /// on_leave_area and on_inside_area functions. The name of the area is passed in the
+    /// "area" property of the character.
+    fn process_areas(&mut self) {
+        let mut events = vec![];
+
+        for (instance_id, character_id) in &self.characters_ids {
+            let areas = if let Some(cu) = self.context.update.characters.get(instance_id) {
+                self.context
+                    .region
+                    .areas_at(vec2i(cu.position.x as i32, cu.position.y as i32))
+            } else {
+                vec![]
+            };
+
+            let previous = self
+                .character_areas
+                .remove(instance_id)
+                .unwrap_or_default();
+
+            for area in &previous {
+                if !areas.contains(area) {
+                    events.push((*instance_id, *character_id, "on_leave_area", *area));
+                }
+            }
+            for area in &areas {
+                let function = if previous.contains(area) {
+                    "on_inside_area"
+                } else {
+                    "on_enter_area"
+                };
+                events.push((*instance_id, *character_id, function, *area));
+            }
+
+            if !areas.is_empty() {
+                self.character_areas.insert(*instance_id, areas);
+            }
+        }
+
+        if events.is_empty() {
+            return;
+        }
+
+        // Look up the names before the context is swapped out.
+        let events: Vec<(Uuid, Uuid, &str, String)> = events
+            .into_iter()
+            .filter_map(|(instance_id, character_id, function, area)| {
+                self.context
+                    .region
+                    .areas
+                    .get(&area)
+                    .map(|area| (instance_id, character_id, function, area.name.clone()))
+            })
+            .collect();
+
+        self.context.swap();
+
+        for (instance_id, character_id, function, area) in events {
+            if let Some(object) = self.sandbox.objects.get_mut(&instance_id) {
+                object.set("area".to_string(), TheValue::Text(area));
+            }
+
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), instance_id);
+
+            if let Some(template) = self.characters.get_mut(&character_id) {
+                template.execute(function.to_string(), &mut self.sandbox);
+            }
+            if let Some(instance) = self.characters_instances.get_mut(&instance_id) {
+                instance.execute(function.to_string(), &mut self.sandbox);
+            }
+        }
+
+        self.process_item_events();
+
+        self.context.swap();
+    }
+
+    /// Executes the item functions requested by the item calls (on_take, on_drop, on_use and
+    /// on_equip) on the item template and instance. The item is available as "self" and the
+    /// character using it as "user". Must be called while the context is swapped in. Events
+    /// requested by these functions are executed after the next code execution.
+    fn process_item_events(&mut self) {
+        let events = with_context(|context| std::mem::take(&mut context.item_events));
+
+        for (item_id, function, character) in events {
+            let template_id = self
+                .items_ids
+                .iter()
+                .find(|(id, _)| *id == item_id)
+                .map(|(_, template_id)| *template_id);
+
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), item_id);
+            self.sandbox.aliases.insert("user".to_string(), character);
+
+            if let Some(template_id) = template_id {
+                if let Some(template) = self.items.get_mut(&template_id) {
+                    template.execute(function.clone(), &mut self.sandbox);
+                }
+            }
+            if let Some(instance) = self.items_instances.get_mut(&item_id) {
+                instance.execute(function, &mut self.sandbox);
+            }
+        }
+
+        self.sandbox.aliases.remove("user");
+    }
+
+    /// Moves the sandbox objects of the characters which got pushed aside to their new
+    /// positions.
+    fn apply_pushes(&mut self) {
+        for (character, position) in std::mem::take(&mut self.context.pushed) {
+            if let Some(object) = self.sandbox.objects.get_mut(&character) {
+                if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                    *p = vec3f(position.x, position.y, p.z);
+                }
+            }
+        }
+    }
+
+    /// Reads the "passable", "pushable" and "swim" properties of the characters into the
+    /// context.
+    fn update_character_flags(&mut self) {
+        self.context.passable.clear();
+        self.context.pushable.clear();
+        self.context.swimmers.clear();
+
+        for (id, object) in &self.sandbox.objects {
+            if let Some(TheValue::Bool(true)) = object.get(&"passable".into()) {
+                self.context.passable.insert(*id);
+            }
+            if let Some(TheValue::Bool(true)) = object.get(&"pushable".into()) {
+                self.context.pushable.insert(*id);
+            }
+            if let Some(TheValue::Bool(true)) = object.get(&"swim".into()) {
+                self.context.swimmers.insert(*id);
+            }
+        }
+    }
+
+    /// Reads the "light" properties of the characters and items into the update and
+    /// recomputes the light map.
+    fn update_lights(&mut self) {
+        for (id, cu) in self.context.update.characters.iter_mut() {
+            if let Some(object) = self.sandbox.objects.get(id) {
+                cu.light = object
+                    .get(&"light".into())
+                    .and_then(|v| v.to_i32())
+                    .unwrap_or(0);
+            }
+        }
+        for (id, item) in self.context.update.items.iter_mut() {
+            if let Some(object) = self.sandbox.objects.get(id) {
+                item.light = object
+                    .get(&"light".into())
+                    .and_then(|v| v.to_i32())
+                    .unwrap_or(0);
+            }
+        }
+        self.light_map = self.compute_light_map();
+    }
+
+    /// Returns the light map computed at the end of the last tick.
+    pub fn light_map(&self) -> &LightMap {
+        &self.light_map
+    }
+
+    /// Computes the light map of the region from the daylight of the world and the light
+    /// sources on the tiles, the living characters and the items lying in the region.
+    fn compute_light_map(&self) -> LightMap {
+        let ambient = lighting::ambient_light(self.context.world.daylight());
+        if ambient >= 1.0 {
+            return LightMap::new(ambient);
+        }
+
+        let region = &self.context.region;
+        let mut lights = lighting::tile_lights(region, &self.context.tile_flags);
+
+        // Sort by id to keep the lights deterministic.
+        let mut sources: Vec<(Uuid, Light)> = self
+            .context
+            .update
+            .characters
+            .iter()
+            .filter(|(_, cu)| !cu.dead && cu.light > 0)
+            .map(|(id, cu)| {
+                let position = vec2i(cu.position.x as i32, cu.position.y as i32);
+                (*id, Light::new(position, cu.light))
+            })
+            .chain(
+                self.context
+                    .update
+                    .items
+                    .iter()
+                    .filter(|(_, item)| item.light > 0)
+                    .map(|(id, item)| {
+                        let position = vec2i(item.position.x as i32, item.position.y as i32);
+                        (*id, Light::new(position, item.light))
+                    }),
+            )
+            .collect();
+        sources.sort_by_key(|(id, _)| *id);
+        lights.extend(sources.into_iter().map(|(_, light)| light));
+
+        LightMap::compute(
+            region,
+            &self.context.tiles,
+            &self.context.tile_flags,
+            &lights,
+            ambient,
+        )
+    }
+
+    /// Returns the region of this instance.
+    pub fn region(&self) -> &Region {
+        &self.context.region
+    }
+
+    /// Sets the region of this instance. Called after live updates from the editor.
+    pub fn set_region(&mut self, region: Region) {
+        self.context.region = region;
+    }
+
+    /// Sets the tiles and tile flags used by this instance.
+    pub fn set_tiles(
+        &mut self,
+        tiles: Arc<FxHashMap<Uuid, TheRGBATile>>,
+        tile_flags: Arc<FxHashMap<Uuid, TileFlags>>,
+    ) {
+        self.context.tiles = tiles;
+        self.context.tile_flags = tile_flags;
+    }
+
+    /// Sets the code library used by this instance.
+    pub fn set_library(&mut self, library: Arc<CodeLibrary>) {
+        self.context.library = library;
+        self.context.functions.clear();
+    }
+
+    /// Returns the current update of this region.
+    pub fn update(&self) -> &RegionUpdate {
+        &self.context.update
     }
 
     /// Create an instance from json.
@@ -118,52 +773,150 @@ impl RegionInstance {
     ) {
         let delta = self.redraw_ms as f32 / self.tick_ms as f32;
 
-        if let Some(region) = REGIONS.read().unwrap().get(&self.id) {
-            let grid_size = region.grid_size as f32;
-
-            tiledrawer.draw_region(buffer, region, anim_counter, ctx);
+        // In field of view mode only the tiles seen by the player are shown.
+        let visible = if server_ctx.fov_mode {
+            server_ctx.curr_player.and_then(|player| {
+                let cu = self.context.update.characters.get(&player)?;
+                let radius = self
+                    .sandbox
+                    .objects
+                    .get(&player)
+                    .map(vision::vision_radius)
+                    .unwrap_or(vision::DEFAULT_VISION);
+                let origin = vec2i(cu.position.x as i32, cu.position.y as i32);
+                Some(vision::field_of_view(&self.context, origin, radius))
+            })
+        } else {
+            None
+        };
+
+        let region = &self.context.region;
+        let grid_size = region.grid_size as f32;
+
+        tiledrawer.draw_region(buffer, region, anim_counter, ctx);
+
+        for item in self.context.update.items.values_mut() {
+            let draw_pos = vec2i(
+                (item.position.x * grid_size) as i32,
+                (item.position.y * grid_size) as i32,
+            );
+
+            if !tiledrawer.draw_tile_at_pixel(draw_pos, buffer, item.tile_id, anim_counter, ctx) {
+                if let Some(found_id) = tiledrawer.get_tile_id_by_name(item.tile_name.clone()) {
+                    item.tile_id = found_id;
+                    tiledrawer.draw_tile_at_pixel(draw_pos, buffer, found_id, anim_counter, ctx);
+                }
+            }
+        }
 
-            if let Some(update) = UPDATES.write().unwrap().get_mut(&self.id) {
-                for (id, character) in &mut update.characters{
+        for (id, character) in &mut self.context.update.characters {
+            if character.dead {
+                continue;
+            }
 
-                    let draw_pos = if let Some((start, end)) = &mut character.moving {
+            let draw_pos = if let Some((start, end)) = &mut character.moving {
 
-                        // pub fn smoothstep(e0: f32, e1: f32, x: f32) -> f32 {
-                        //     let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0);
-                        //     t * t * (3.0 - 2.0 * t)
-                        // }
+                // pub fn smoothstep(e0: f32, e1: f32, x: f32) -> f32 {
+                //     let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0);
+                //     t * t * (3.0 - 2.0 * t)
+                // }
 
-                        let sum = (delta + character.move_delta).clamp(0.0, 1.0);
-                        //let d = smoothstep(0.0, 1.0, sum);//.clamp(0.0, 1.0);
-                        let d = if sum < 0.5 {
-                            2.0 * sum * sum
-                        } else {
-                            1.0 - (-2.0 * sum + 2.0).powi(2) / 2.0
-                        };
-                        let x = start.x * (1.0 - d) + end.x * d;
-                        let y = start.y * (1.0 - d) + end.y * d;
-                        character.move_delta = sum;
-                        vec2i((x * grid_size).round() as i32, (y * grid_size).round() as i32)
-                    } else {
-                        vec2i((character.position.x * grid_size) as i32, (character.position.y * grid_size) as i32)
-                    };
-
-                    //println!("moving: {:?}", draw_pos);
-
-                    if !tiledrawer.draw_tile_at_pixel(
+                let sum = (delta + character.move_delta).clamp(0.0, 1.0);
+                //let d = smoothstep(0.0, 1.0, sum);//.clamp(0.0, 1.0);
+                let d = if sum < 0.5 {
+                    2.0 * sum * sum
+                } else {
+                    1.0 - (-2.0 * sum + 2.0).powi(2) / 2.0
+                };
+                let x = start.x * (1.0 - d) + end.x * d;
+                let y = start.y * (1.0 - d) + end.y * d;
+                character.move_delta = sum;
+                vec2i((x * grid_size).round() as i32, (y * grid_size).round() as i32)
+            } else {
+                vec2i((character.position.x * grid_size) as i32, (character.position.y * grid_size) as i32)
+            };
+
+            //println!("moving: {:?}", draw_pos);
+
+            if !tiledrawer.draw_tile_at_pixel(
+                draw_pos,
+                buffer,
+                character.tile_id,
+                anim_counter,
+                ctx,
+            ) {
+                if let Some(found_id) =
+                    tiledrawer.get_tile_id_by_name(character.tile_name.clone())
+                {
+                    character.tile_id = found_id;
+                    tiledrawer.draw_tile_at_pixel(
                         draw_pos,
                         buffer,
-                        character.tile_id,
+                        found_id,
+                        anim_counter,
+                        ctx,
+                    );
+                } else {
+                    //println!("RegionInstance::draw: Tile not found: {}", name);
+                }
+            }
+
+            // Show the combat effects of the last tick.
+            for effect in &character.effects {
+                let color = match effect {
+                    CharacterEffect::Damage(_) => [255, 0, 0, 255],
+                    CharacterEffect::Heal(_) => [0, 255, 0, 255],
+                    CharacterEffect::Respawn => [255, 255, 0, 255],
+                    CharacterEffect::Miss | CharacterEffect::Death => [128, 128, 128, 255],
+                };
+                tiledrawer.draw_tile_outline_at_pixel(draw_pos, buffer, color, ctx);
+            }
+
+            if Some(*id) == server_ctx.curr_character_instance {
+                tiledrawer.draw_tile_outline_at_pixel(
+                    draw_pos,
+                    buffer,
+                    WHITE,
+                    ctx,
+                );
+            } else if Some(*id) == server_ctx.curr_character {
+                tiledrawer.draw_tile_outline_at_pixel(
+                    draw_pos,
+                    buffer,
+                    [128, 128, 128, 255],
+                    ctx,
+                );
+            }
+        }
+
+        // Darken the region at night, lit by the light sources.
+        if !self.light_map.is_full_bright() {
+            tiledrawer.draw_lighting(buffer, &self.context.region, &self.light_map);
+        }
+
+        if let Some(visible) = &visible {
+            tiledrawer.draw_fog(buffer, region, visible);
+        }
+        /*
+        for c in self.sandbox.objects.values_mut() {
+            if let Some(TheValue::Position(p)) = c.get(&"position".into()).cloned() {
+                if let Some(TheValue::Tile(name, id)) = c.get_mut(&"tile".into()) {
+                    //println!("p {:?} s {:?}", p, name);
+
+                    if !tiledrawer.draw_tile(
+                        vec2i(p.x as i32, p.y as i32),
+                        buffer,
+                        region.grid_size,
+                        *id,
                         anim_counter,
                         ctx,
                     ) {
-                        if let Some(found_id) =
-                            tiledrawer.get_tile_id_by_name(character.tile_name.clone())
-                        {
-                            character.tile_id = found_id;
-                            tiledrawer.draw_tile_at_pixel(
-                                draw_pos,
+                        if let Some(found_id) = tiledrawer.get_tile_id_by_name(name.clone()) {
+                            *id = found_id;
+                            tiledrawer.draw_tile(
+                                vec2i(p.x as i32, p.y as i32),
                                 buffer,
+                                region.grid_size,
                                 found_id,
                                 anim_counter,
                                 ctx,
@@ -172,78 +925,31 @@ impl RegionInstance {
                             //println!("RegionInstance::draw: Tile not found: {}", name);
                         }
                     }
-
-                    if Some(*id) == server_ctx.curr_character_instance {
-                        tiledrawer.draw_tile_outline_at_pixel(
-                            draw_pos,
-                            buffer,
-                            WHITE,
-                            ctx,
-                        );
-                    } else if Some(*id) == server_ctx.curr_character {
-                        tiledrawer.draw_tile_outline_at_pixel(
-                            draw_pos,
-                            buffer,
-                            [128, 128, 128, 255],
-                            ctx,
-                        );
-                    }
                 }
             }
-            /*
-            for c in self.sandbox.objects.values_mut() {
-                if let Some(TheValue::Position(p)) = c.get(&"position".into()).cloned() {
-                    if let Some(TheValue::Tile(name, id)) = c.get_mut(&"tile".into()) {
-                        //println!("p {:?} s {:?}", p, name);
-
-                        if !tiledrawer.draw_tile(
-                            vec2i(p.x as i32, p.y as i32),
-                            buffer,
-                            region.grid_size,
-                            *id,
-                            anim_counter,
-                            ctx,
-                        ) {
-                            if let Some(found_id) = tiledrawer.get_tile_id_by_name(name.clone()) {
-                                *id = found_id;
-                                tiledrawer.draw_tile(
-                                    vec2i(p.x as i32, p.y as i32),
-                                    buffer,
-                                    region.grid_size,
-                                    found_id,
-                                    anim_counter,
-                                    ctx,
-                                );
-                            } else {
-                                //println!("RegionInstance::draw: Tile not found: {}", name);
-                            }
-                        }
-                    }
-                }
 
-                if Some(c.id) == server_ctx.curr_character_instance {
-                    if let Some(TheValue::Position(p)) = c.get(&"position".into()) {
-                        tiledrawer.draw_tile_outline(
-                            vec2i(p.x as i32, p.y as i32),
-                            buffer,
-                            region.grid_size,
-                            WHITE,
-                            ctx,
-                        );
-                    }
-                } else if Some(c.id) == server_ctx.curr_character {
-                    if let Some(TheValue::Position(p)) = c.get(&"position".into()) {
-                        tiledrawer.draw_tile_outline(
-                            vec2i(p.x as i32, p.y as i32),
-                            buffer,
-                            region.grid_size,
-                            [128, 128, 128, 255],
-                            ctx,
-                        );
-                    }
+            if Some(c.id) == server_ctx.curr_character_instance {
+                if let Some(TheValue::Position(p)) = c.get(&"position".into()) {
+                    tiledrawer.draw_tile_outline(
+                        vec2i(p.x as i32, p.y as i32),
+                        buffer,
+                        region.grid_size,
+                        WHITE,
+                        ctx,
+                    );
                 }
-            }*/
-        }
+            } else if Some(c.id) == server_ctx.curr_character {
+                if let Some(TheValue::Position(p)) = c.get(&"position".into()) {
+                    tiledrawer.draw_tile_outline(
+                        vec2i(p.x as i32, p.y as i32),
+                        buffer,
+                        region.grid_size,
+                        [128, 128, 128, 255],
+                        ctx,
+                    );
+                }
+            }
+        }*/
     }
 
     /// Insert a (TheCodePackage) to the region.
@@ -256,6 +962,7 @@ impl RegionInstance {
             }
         }
 
+        self.context.swap();
         for id in instance_ids {
             self.sandbox.clear_runtime_states();
             self.sandbox.aliases.insert("self".to_string(), id);
@@ -265,40 +972,20 @@ impl RegionInstance {
                 inst.execute("init".to_string(), &mut self.sandbox);
             }
         }
+        self.context.swap();
 
         self.characters.insert(character.id, character);
     }
 
     /// Adds a character instance to the region.
     pub fn add_character_instance(&mut self, mut character: Character) -> Option<Uuid> {
-        let mut package = TheCodePackage::new();
-        package.id = character.id;
-
-        let mut module_id = None;
-
-        let mut compiler = TheCompiler::new();
-
-        for grid in character.instance.grids.values_mut() {
-            let rc = compiler.compile(grid);
-            if let Ok(mut module) = rc {
-                module.name = grid.name.clone();
-                println!(
-                    "RegionInstance::add_character_instance: Compiled grid module: {}",
-                    grid.name
-                );
-                module_id = Some(module.id);
-                package.insert_module(module.name.clone(), module);
-            } else {
-                println!(
-                    "RegionInstance::add_character_instance: Failed to compile grid: {}",
-                    grid.name
-                );
-            }
-        }
+        let (mut package, module_id) =
+            self.compile_instance_bundle(character.id, &mut character.instance);
 
         let mut o = TheCodeObject::new();
         o.id = character.id;
 
+        self.context.swap();
         self.sandbox.clear_runtime_states();
         self.sandbox.aliases.insert("self".to_string(), o.id);
 
@@ -309,12 +996,14 @@ impl RegionInstance {
         }
 
         package.execute("init".to_string(), &mut self.sandbox);
+        self.context.swap();
 
         // Add the character to the update struct.
         if let Some(object) = self.sandbox.objects.get_mut(&character.id) {
             let mut character_update = CharacterUpdate::new();
+            let mut position = vec2f(0.0, 0.0);
             if let Some(TheValue::Position(p)) = object.get(&"position".into()) {
-                character_update.position = vec2f(p.x, p.y);
+                position = vec2f(p.x, p.y);
             }
             if let Some(TheValue::Text(t)) = object.get(&"name".into()) {
                 character_update.name = t.clone();
@@ -323,55 +1012,490 @@ impl RegionInstance {
                 character_update.tile_name = name.clone();
                 character_update.tile_id = *id;
             }
-
-            if let Some(update) = UPDATES.write().unwrap().get_mut(&self.id) {
-                update.characters.insert(character.id, character_update);
+            (character_update.hp, character_update.max_hp) = combat::hit_points(object);
+            character_update.light = object
+                .get(&"light".into())
+                .and_then(|v| v.to_i32())
+                .unwrap_or(0);
+
+            // Dead characters respawn at their initial position.
+            if object.get(&"spawn".into()).is_none() {
+                object.set(
+                    "spawn".to_string(),
+                    TheValue::Position(vec3f(position.x, position.y, 0.0)),
+                );
             }
+
+            self.context
+                .update
+                .characters
+                .insert(character.id, character_update);
+            self.context.set_position(character.id, position, false);
         }
+        self.update_character_flags();
 
         self.characters_ids
             .push((character.id, character.character_id));
         self.characters_instances.insert(package.id, package);
+        self.characters_data.insert(character.id, character);
 
         module_id
     }
 
     /// Updates a character instance.
     pub fn update_character_instance_bundle(&mut self, character: Uuid, mut bundle: TheCodeBundle) {
-        if let Some(existing_package) = self.characters_instances.get_mut(&character) {
-            let mut package = TheCodePackage::new();
+        if self.characters_instances.contains_key(&character) {
+            let (mut package, _) = self.compile_instance_bundle(character, &mut bundle);
+
+            self.context.swap();
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), character);
+
+            package.execute("init".to_string(), &mut self.sandbox);
+            self.context.swap();
+
+            self.characters_instances.insert(character, package);
+            if let Some(data) = self.characters_data.get_mut(&character) {
+                data.instance = bundle;
+            }
+            self.update_character_flags();
+        }
+    }
+
+    /// Insert an item (TheCodePackage) to the region.
+    pub fn insert_item(&mut self, mut item: TheCodePackage) {
+        // We collect all instances of this item and execute the init function on them.
+        let mut instance_ids = vec![];
+        for o in self.sandbox.objects.values() {
+            if o.package_id == item.id {
+                instance_ids.push(o.id);
+            }
+        }
+
+        self.context.swap();
+        for id in instance_ids {
+            self.sandbox.clear_runtime_states();
+            self.sandbox.aliases.insert("self".to_string(), id);
+            item.execute("init".to_string(), &mut self.sandbox);
+
+            if let Some(inst) = self.items_instances.get_mut(&id) {
+                inst.execute("init".to_string(), &mut self.sandbox);
+            }
+        }
+        self.context.swap();
+
+        self.items.insert(item.id, item);
+    }
+
+    /// Adds an item instance to the region, lying on the ground.
+    pub fn add_item_instance(&mut self, mut item: Item) -> Option<Uuid> {
+        let (mut package, module_id) = self.compile_instance_bundle(item.id, &mut item.instance);
+
+        let mut o = TheCodeObject::new();
+        o.id = item.id;
+
+        self.context.swap();
+        self.sandbox.clear_runtime_states();
+        self.sandbox.aliases.insert("self".to_string(), o.id);
+
+        if let Some(template) = self.items.get_mut(&item.item_id) {
+            o.package_id = template.id;
+            self.sandbox.add_object(o);
+            template.execute("init".to_string(), &mut self.sandbox);
+        }
+
+        package.execute("init".to_string(), &mut self.sandbox);
+        self.context.swap();
+
+        // Add the item to the update struct.
+        if let Some(object) = self.sandbox.objects.get(&item.id) {
+            self.context
+                .update
+                .items
+                .insert(item.id, ItemUpdate::from_object(object));
+        }
 
-            let mut compiler = TheCompiler::new();
+        self.items_ids.push((item.id, item.item_id));
+        self.items_instances.insert(package.id, package);
+        self.items_data.insert(item.id, item);
 
-            for grid in bundle.grids.values_mut() {
-                let rc = compiler.compile(grid);
-                if let Ok(mut module) = rc {
+        module_id
+    }
+
+    /// Removes the given item instance from the region, from the ground or an inventory.
+    pub fn remove_item_instance(&mut self, item: Uuid) {
+        self.items_instances.remove(&item);
+        self.items_data.remove(&item);
+        self.items_ids.retain(|(instance_id, _)| *instance_id != item);
+        self.context.remove_item(item);
+        self.sandbox.objects.remove(&item);
+    }
+
+    /// Returns the item instance id and the item id for the item lying at the given position.
+    pub fn get_item_at(&self, pos: Vec2i) -> Option<(Uuid, Uuid)> {
+        let instance_id = self.context.item_at(pos, "")?;
+        self.items_ids
+            .iter()
+            .find(|(id, _)| *id == instance_id)
+            .copied()
+    }
+
+    /// Returns the value of the given item instance property.
+    pub fn get_item_property(&self, item: Uuid, property: String) -> Option<TheValue> {
+        self.sandbox
+            .objects
+            .get(&item)
+            .and_then(|o| o.get(&property).cloned())
+    }
+
+    /// Returns the inventory of the given character instance.
+    pub fn get_inventory(&self, character: &Uuid) -> Option<&Inventory> {
+        self.context.inventories.get(character)
+    }
+
+    /// Compiles the custom bundle of a character or item instance into a package. Returns the
+    /// package and the id of the last compiled module (for debugging). Compile errors are
+    /// collected in the diagnostics.
+    fn compile_instance_bundle(
+        &mut self,
+        id: Uuid,
+        bundle: &mut TheCodeBundle,
+    ) -> (TheCodePackage, Option<Uuid>) {
+        let mut package = TheCodePackage::new();
+        package.id = id;
+
+        let mut module_id = None;
+
+        let mut compiler = TheCompiler::new();
+
+        let bundle_id = bundle.id;
+        let bundle_name = bundle.name.clone();
+
+        for grid in bundle.grids.values_mut() {
+            match compiler.compile(grid) {
+                Ok(mut module) => {
                     module.name = grid.name.clone();
-                    println!(
-                        "RegionInstance::add_character_instance: Compiled grid module: {}",
-                        grid.name
-                    );
+                    module_id = Some(module.id);
                     package.insert_module(module.name.clone(), module);
-                } else {
-                    println!(
-                        "RegionInstance::add_character_instance: Failed to compile grid: {}",
-                        grid.name
-                    );
+                }
+                Err(err) => {
+                    self.diagnostics.push(CompileDiagnostic::new(
+                        bundle_id,
+                        bundle_name.clone(),
+                        grid,
+                        &err,
+                    ));
                 }
             }
+        }
 
-            self.sandbox.clear_runtime_states();
-            self.sandbox.aliases.insert("self".to_string(), character);
+        (package, module_id)
+    }
 
-            package.execute("init".to_string(), &mut self.sandbox);
+    /// Returns and clears the compile errors collected since the last call.
+    pub fn take_diagnostics(&mut self) -> Vec<CompileDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Returns and clears the runtime errors collected since the last call.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.context.errors)
+    }
+
+    /// Takes a snapshot of the runtime state of this instance.
+    pub fn snapshot(&self) -> RegionSnapshot {
+        let mut characters = vec![];
+        for (instance_id, _) in &self.characters_ids {
+            if let Some(character) = self.characters_data.get(instance_id) {
+                characters.push(character.clone());
+            }
+        }
+
+        let mut items = vec![];
+        for (instance_id, _) in &self.items_ids {
+            if let Some(item) = self.items_data.get(instance_id) {
+                items.push(item.clone());
+            }
+        }
+
+        RegionSnapshot {
+            id: self.id,
+            region: self.context.region.clone(),
+            update: self.context.update.clone(),
+            objects: self.sandbox.objects.clone(),
+            characters,
+            rng: self.context.rng.clone(),
+            players: self.players.clone(),
+            actions: self.actions.clone(),
+            paths: self.context.paths.clone(),
+            unreachable: self.context.unreachable.clone(),
+            character_areas: self.character_areas.clone(),
+            items,
+            inventories: self.context.inventories.clone(),
+            events: self.events.clone(),
+            timers: self.context.timers.clone(),
+            dead: self.context.dead.clone(),
+            move_delays: self.context.move_delays.clone(),
+        }
+    }
+
+    /// Restores the runtime state of this instance from the given snapshot. The character
+    /// templates have to be inserted already. The init functions are not executed, the
+    /// character objects are taken as is from the snapshot.
+    pub fn restore(&mut self, snapshot: RegionSnapshot) {
+        self.sandbox.objects = snapshot.objects;
+
+        self.characters_instances.clear();
+        self.characters_ids.clear();
+        self.characters_data.clear();
+
+        for mut character in snapshot.characters {
+            let (package, _) = self.compile_instance_bundle(character.id, &mut character.instance);
+
+            self.characters_ids
+                .push((character.id, character.character_id));
+            self.characters_instances.insert(package.id, package);
+            self.characters_data.insert(character.id, character);
+        }
+
+        self.items_instances.clear();
+        self.items_ids.clear();
+        self.items_data.clear();
+
+        for mut item in snapshot.items {
+            let (package, _) = self.compile_instance_bundle(item.id, &mut item.instance);
+
+            self.items_ids.push((item.id, item.item_id));
+            self.items_instances.insert(package.id, package);
+            self.items_data.insert(item.id, item);
+        }
+
+        self.context.region = snapshot.region;
+        self.context.update = snapshot.update;
+        self.context.rng = snapshot.rng;
+        self.context.paths = snapshot.paths;
+        self.context.unreachable = snapshot.unreachable;
+        self.context.inventories = snapshot.inventories;
+        self.context.timers = snapshot.timers;
+        self.context.dead = snapshot.dead;
+        self.context.move_delays = snapshot.move_delays;
+        self.context.deaths.clear();
+        self.context.item_events.clear();
+        self.context.rebuild_occupancy();
+        self.update_character_flags();
+        self.light_map = self.compute_light_map();
+
+        self.players = snapshot.players;
+        self.actions = snapshot.actions;
+        self.character_areas = snapshot.character_areas;
+        self.events = snapshot.events;
+        self.context.events.clear();
+    }
+
+    /// Returns true if the given character instance is located in this region.
+    pub fn has_character_instance(&self, character: &Uuid) -> bool {
+        self.characters_data.contains_key(character)
+    }
+
+    /// Sets if the given character instance is controlled by a player.
+    pub fn set_player(&mut self, character: Uuid, player: bool) {
+        self.players.retain(|id| *id != character);
+        if player {
+            self.players.push(character);
+        }
+    }
+
+    /// Returns true if the given character instance is controlled by a player.
+    pub fn is_player(&self, character: &Uuid) -> bool {
+        self.players.contains(character)
+    }
+
+    /// Queues an action of a player character, it is executed during the next tick.
+    pub fn queue_action(&mut self, character: Uuid, action: PlayerAction) {
+        self.actions.push((character, action));
+    }
+
+    /// Moves the character instance by the given offset if the destination is free. Returns
+    /// true on success.
+    pub fn move_character(&mut self, character: Uuid, offset: Vec2i) -> bool {
+        if let Some(object) = self.sandbox.objects.get_mut(&character) {
+            if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                let x = p.x + offset.x as f32;
+                let y = p.y + offset.y as f32;
+
+                if self.context.try_move(character, p.xy(), vec2f(x, y)) {
+                    *p = vec3f(x, y, p.z);
+                    self.apply_pushes();
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Places the character instance at the given position without any checks.
+    pub fn set_character_position(&mut self, character: Uuid, position: Vec2f) {
+        if let Some(object) = self.sandbox.objects.get_mut(&character) {
+            if let Some(TheValue::Position(p)) = object.get_mut(&"position".into()) {
+                *p = vec3f(position.x, position.y, p.z);
+            } else {
+                object.set(
+                    "position".to_string(),
+                    TheValue::Position(vec3f(position.x, position.y, 0.0)),
+                );
+            }
+        }
+
+        self.context.set_position(character, position, false);
+    }
+
+    /// Returns the transfers to other regions requested during the last tick.
+    pub fn take_transfers(&mut self) -> Vec<(Uuid, String, Vec2f)> {
+        std::mem::take(&mut self.context.transfers)
+    }
+
+    /// Removes the given character instance from the region and returns it, including its
+    /// sandbox object and compiled package, for a transfer into another region.
+    pub fn take_character_instance(&mut self, character: Uuid) -> Option<CharacterTransfer> {
+        if !self.characters_data.contains_key(&character)
+            || !self.sandbox.objects.contains_key(&character)
+            || !self.characters_instances.contains_key(&character)
+        {
+            return None;
+        }
+
+        let data = self.characters_data.remove(&character)?;
+        let object = self.sandbox.objects.remove(&character)?;
+        let package = self.characters_instances.remove(&character)?;
+
+        self.characters_ids
+            .retain(|(instance_id, _)| *instance_id != character);
+        self.actions.retain(|(id, _)| *id != character);
+        let update = self.context.update.characters.get(&character).cloned();
+        let timers: Vec<Timer> = self
+            .context
+            .timers
+            .iter()
+            .filter(|timer| timer.character == character)
+            .cloned()
+            .collect();
+        self.context.remove_character(character);
+
+        let player = self.is_player(&character);
+        self.players.retain(|id| *id != character);
+        self.character_areas.remove(&character);
+
+        // The carried items travel with the character.
+        let inventory = self.context.inventories.remove(&character);
+        let mut items = vec![];
+        if let Some(inventory) = &inventory {
+            for item_id in &inventory.items {
+                if let (Some(item), Some(object), Some(package)) = (
+                    self.items_data.remove(item_id),
+                    self.sandbox.objects.remove(item_id),
+                    self.items_instances.remove(item_id),
+                ) {
+                    items.push((item, object, package));
+                }
+                self.items_ids.retain(|(instance_id, _)| instance_id != item_id);
+            }
+        }
+
+        Some(CharacterTransfer {
+            character: data,
+            object,
+            package,
+            update,
+            player,
+            inventory,
+            items,
+            timers,
+        })
+    }
+
+    /// Inserts a character instance transferred from another region at the given position.
+    /// The init functions are not executed, the character keeps its state. If the position
+    /// is blocked or occupied the transfer is returned.
+    pub fn transfer_character_into(
+        &mut self,
+        mut transfer: CharacterTransfer,
+        position: Vec2f,
+    ) -> Option<CharacterTransfer> {
+        let swims = matches!(
+            transfer.object.get(&"swim".into()),
+            Some(TheValue::Bool(true))
+        );
+        let tile = vec2i(position.x as i32, position.y as i32);
+        if !self.context.can_enter_tile(position, swims)
+            || self
+                .context
+                .occupant_at(tile, transfer.character.id)
+                .is_some()
+        {
+            return Some(transfer);
+        }
+
+        if let Some(TheValue::Position(p)) = transfer.object.get_mut(&"position".into()) {
+            *p = vec3f(position.x, position.y, p.z);
+        }
 
-            *existing_package = package;
+        self.restore_character_instance(transfer);
+        None
+    }
+
+    /// Puts a character instance taken out of this region back at the position of its
+    /// sandbox object, i.e. after a failed transfer.
+    pub fn restore_character_instance(&mut self, transfer: CharacterTransfer) {
+        let id = transfer.character.id;
+        let position = match transfer.object.get(&"position".into()) {
+            Some(TheValue::Position(p)) => vec2f(p.x, p.y),
+            _ => Vec2f::zero(),
+        };
+
+        self.context
+            .update
+            .characters
+            .insert(id, transfer.update.unwrap_or_default());
+        self.context.set_position(id, position, false);
+        self.sandbox.objects.insert(id, transfer.object);
+        self.characters_ids
+            .push((id, transfer.character.character_id));
+        self.characters_instances.insert(id, transfer.package);
+        self.characters_data.insert(id, transfer.character);
+
+        if transfer.player {
+            self.players.push(id);
+        }
+
+        for (item, object, package) in transfer.items {
+            self.sandbox.objects.insert(item.id, object);
+            self.items_ids.push((item.id, item.item_id));
+            self.items_instances.insert(item.id, package);
+            self.items_data.insert(item.id, item);
+        }
+        if let Some(inventory) = transfer.inventory {
+            self.context.inventories.insert(id, inventory);
         }
+        self.context.timers.extend(transfer.timers);
+
+        self.update_character_flags();
     }
 
     /// Removes the given character instance from the region.
     pub fn remove_character_instance(&mut self, character: Uuid) {
+        // The carried items are removed together with the character.
+        if let Some(inventory) = self.context.inventories.remove(&character) {
+            for item in inventory.items {
+                self.remove_item_instance(item);
+            }
+        }
+
         self.characters_instances.remove(&character);
+        self.characters_data.remove(&character);
+        self.context.remove_character(character);
+        self.players.retain(|id| *id != character);
+        self.actions.retain(|(id, _)| *id != character);
+        self.character_areas.remove(&character);
         self.characters_ids
             .retain(|(instance_id, _)| *instance_id != character);
         self.sandbox.objects.remove(&character);
@@ -0,0 +1,95 @@
+use crate::prelude::*;
+use crate::server::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use theframework::prelude::*;
+
+/// The complete runtime state of a server. Used for savegames and for rewinding the server in
+/// the creator.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerSnapshot {
+    pub seed: u64,
+    pub world: World,
+    pub anim_counter: usize,
+
+    pub regions: Vec<RegionSnapshot>,
+
+    /// The player actions received before the snapshot was taken.
+    #[serde(default)]
+    pub input_log: Vec<InputEvent>,
+}
+
+impl ServerSnapshot {
+    /// Create a snapshot from json.
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    /// Convert the snapshot to json.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap_or_default()
+    }
+}
+
+/// The runtime state of a region instance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RegionSnapshot {
+    pub id: Uuid,
+
+    /// The region as currently used by the server.
+    pub region: Region,
+
+    /// The current update of the region, including pending character movement.
+    pub update: RegionUpdate,
+
+    /// The sandbox objects of all instances in the region.
+    pub objects: FxHashMap<Uuid, TheCodeObject>,
+
+    /// The character instances in execution order.
+    pub characters: Vec<Character>,
+
+    pub rng: ChaCha8Rng,
+
+    /// The player controlled character instances.
+    #[serde(default)]
+    pub players: Vec<Uuid>,
+
+    /// The queued player actions.
+    #[serde(default)]
+    pub actions: Vec<(Uuid, PlayerAction)>,
+
+    /// The cached paths of the characters walking towards a target.
+    #[serde(default)]
+    pub paths: FxHashMap<Uuid, (Vec2i, Vec<Vec2i>)>,
+
+    /// The characters which found no path to their target.
+    #[serde(default)]
+    pub unreachable: FxHashMap<Uuid, (Vec2i, i64)>,
+
+    /// The areas each character instance is currently inside of.
+    #[serde(default)]
+    pub character_areas: FxHashMap<Uuid, Vec<Uuid>>,
+
+    /// The item instances in creation order.
+    #[serde(default)]
+    pub items: Vec<Item>,
+
+    /// The inventories of the characters.
+    #[serde(default)]
+    pub inventories: FxHashMap<Uuid, Inventory>,
+
+    /// The events to deliver in the next tick.
+    #[serde(default)]
+    pub events: Vec<CharacterEvent>,
+
+    /// The running timers of the characters.
+    #[serde(default)]
+    pub timers: Vec<Timer>,
+
+    /// The dead character instances along with the world tick at which they respawn.
+    #[serde(default)]
+    pub dead: FxHashMap<Uuid, Option<i64>>,
+
+    /// The character instances crossing slow terrain with the tick they can move again.
+    #[serde(default)]
+    pub move_delays: FxHashMap<Uuid, i64>,
+}
@@ -0,0 +1,17 @@
+use theframework::prelude::*;
+
+/// A timer of a character instance, started with StartTimer. When the world tick counter
+/// reaches the tick of the timer the given function of the character is executed.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Timer {
+    pub character: Uuid,
+
+    /// The name of the function (grid) to execute.
+    pub function: String,
+
+    /// The world tick at which the timer fires.
+    pub tick: i64,
+
+    /// If set the timer restarts with the given interval after firing.
+    pub interval: Option<i64>,
+}
@@ -4,6 +4,10 @@ use theframework::prelude::*;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RegionUpdate {
     pub characters: FxHashMap<Uuid, CharacterUpdate>,
+
+    /// The items lying in the region (not the ones carried by characters).
+    #[serde(default)]
+    pub items: FxHashMap<Uuid, ItemUpdate>,
 }
 
 impl Default for RegionUpdate {
@@ -16,12 +20,14 @@ impl RegionUpdate {
     pub fn new() -> Self {
         Self {
             characters: FxHashMap::default(),
+            items: FxHashMap::default(),
         }
     }
 
     /// Sets up the region instance.
     pub fn clear(&mut self) {
         self.characters.clear();
+        self.items.clear();
     }
 }
 
@@ -34,6 +40,33 @@ pub struct CharacterUpdate {
     pub position: Vec2f,
     pub moving: Option<(Vec2f, Vec2f)>,
     pub move_delta: f32,
+
+    #[serde(default)]
+    pub hp: i32,
+    #[serde(default)]
+    pub max_hp: i32,
+
+    /// Dead characters are not drawn and do not block other characters.
+    #[serde(default)]
+    pub dead: bool,
+
+    /// The combat effects on the character during the last tick.
+    #[serde(default)]
+    pub effects: Vec<CharacterEffect>,
+
+    /// The radius of the light carried by the character, 0 for none.
+    #[serde(default)]
+    pub light: i32,
+}
+
+/// A combat effect on a character, displayed by the clients.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CharacterEffect {
+    Damage(i32),
+    Heal(i32),
+    Miss,
+    Death,
+    Respawn,
 }
 
 impl Default for CharacterUpdate {
@@ -52,6 +85,66 @@ impl CharacterUpdate {
             position: vec2f(0.0, 0.0),
             moving: None,
             move_delta: 0.0,
+
+            hp: 0,
+            max_hp: 0,
+            dead: false,
+            effects: vec![],
+
+            light: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemUpdate {
+    pub tile_id: Uuid,
+    pub tile_name: String,
+
+    pub name: String,
+    pub position: Vec2f,
+
+    /// The radius of the light emitted by the item, 0 for none.
+    #[serde(default)]
+    pub light: i32,
+}
+
+impl Default for ItemUpdate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ItemUpdate {
+    pub fn new() -> Self {
+        Self {
+            tile_id: Uuid::nil(),
+            tile_name: "".to_string(),
+
+            name: "".to_string(),
+            position: vec2f(0.0, 0.0),
+
+            light: 0,
+        }
+    }
+
+    /// Creates the update of an item from the name, tile, position and light properties of its
+    /// sandbox object.
+    pub fn from_object(object: &TheCodeObject) -> Self {
+        let mut item_update = Self::new();
+        if let Some(TheValue::Position(p)) = object.get(&"position".into()) {
+            item_update.position = vec2f(p.x, p.y);
+        }
+        if let Some(TheValue::Text(t)) = object.get(&"name".into()) {
+            item_update.name = t.clone();
+        }
+        if let Some(TheValue::Tile(name, id)) = object.get(&"tile".into()) {
+            item_update.tile_name = name.clone();
+            item_update.tile_id = *id;
+        }
+        if let Some(light) = object.get(&"light".into()).and_then(|v| v.to_i32()) {
+            item_update.light = light;
         }
+        item_update
     }
 }
@@ -0,0 +1,94 @@
+use crate::server::prelude::*;
+use theframework::prelude::*;
+
+/// The vision radius of characters which do not define "vision".
+pub const DEFAULT_VISION: i32 = 8;
+
+/// Returns the vision radius of the character object in tiles.
+pub fn vision_radius(object: &TheCodeObject) -> i32 {
+    object
+        .get(&"vision".into())
+        .and_then(|v| v.to_i32())
+        .unwrap_or(DEFAULT_VISION)
+        .max(0)
+}
+
+/// Returns true if the tile at the given position of the region blocks the view.
+pub fn blocks_vision(context: &RegionContext, pos: Vec2i) -> bool {
+    context
+        .region
+        .blocks_vision(pos, &context.tiles, &context.tile_flags)
+}
+
+/// Returns true if the target position is within the given radius of the viewer and there is
+/// a free line of sight between them.
+pub fn can_see(context: &RegionContext, from: Vec2i, to: Vec2i, radius: i32) -> bool {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    dx * dx + dy * dy <= radius * radius
+        && context
+            .region
+            .line_of_sight(from, to, |pos| blocks_vision(context, pos))
+}
+
+/// Returns the positions of the region visible from the given position.
+pub fn field_of_view(
+    context: &RegionContext,
+    origin: Vec2i,
+    radius: i32,
+) -> FxHashSet<(i32, i32)> {
+    context
+        .region
+        .field_of_view(origin, radius, |pos| blocks_vision(context, pos))
+}
+
+/// Returns the living characters (except the viewer) matching the given role, visible from
+/// the viewer, sorted by their distance and id. A character matches if its "role" property or
+/// its name equals the role, an empty role matches any character.
+pub fn visible_characters(
+    context: &RegionContext,
+    sandbox: &TheCodeSandbox,
+    viewer: Uuid,
+    role: &str,
+) -> Vec<Uuid> {
+    let from = if let Some(cu) = context.update.characters.get(&viewer) {
+        vec2i(cu.position.x as i32, cu.position.y as i32)
+    } else {
+        return vec![];
+    };
+
+    let radius = sandbox
+        .objects
+        .get(&viewer)
+        .map(vision_radius)
+        .unwrap_or(DEFAULT_VISION);
+
+    let mut visible: Vec<(i32, Uuid)> = context
+        .update
+        .characters
+        .iter()
+        .filter(|(id, cu)| {
+            **id != viewer
+                && !cu.dead
+                && (role.is_empty()
+                    || cu.name == role
+                    || matches!(
+                        sandbox.objects.get(id).and_then(|o| o.get(&"role".into())),
+                        Some(TheValue::Text(text)) if text == role
+                    ))
+        })
+        .filter_map(|(id, cu)| {
+            let to = vec2i(cu.position.x as i32, cu.position.y as i32);
+            if can_see(context, from, to, radius) {
+                let dx = to.x - from.x;
+                let dy = to.y - from.y;
+                Some((dx * dx + dy * dy, *id))
+            } else {
+                None
+            }
+        })
+        .collect();
+    visible.sort();
+
+    visible.into_iter().map(|(_, id)| id).collect()
+}
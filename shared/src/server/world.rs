@@ -1,9 +1,68 @@
 //use crate::prelude::*;
 use theframework::prelude::*;
 
+/// The default number of ticks per in-game minute.
+pub(crate) fn default_ticks_per_minute() -> i64 {
+    4
+}
+
+/// The default time of day at tick 0, in minutes after midnight (08:00).
+fn default_start_time() -> i64 {
+    8 * 60
+}
+
+/// The phases of an in-game day.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DayPhase {
+    /// 05:00 - 07:00
+    Dawn,
+    /// 07:00 - 19:00
+    Day,
+    /// 19:00 - 21:00
+    Dusk,
+    /// 21:00 - 05:00
+    Night,
+}
+
+impl DayPhase {
+    /// Returns the name of the phase as seen by the scripts.
+    pub fn name(&self) -> String {
+        match self {
+            DayPhase::Dawn => "dawn",
+            DayPhase::Day => "day",
+            DayPhase::Dusk => "dusk",
+            DayPhase::Night => "night",
+        }
+        .to_string()
+    }
+}
+
+/// An in-game date and time.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct WorldDate {
+    /// The day, starting at 1.
+    pub day: i64,
+    pub hours: i64,
+    pub minutes: i64,
+}
+
+impl std::fmt::Display for WorldDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Day {} {:02}:{:02}", self.day, self.hours, self.minutes)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct World {
     pub tick_counter: i64,
+
+    /// The number of ticks per in-game minute.
+    #[serde(default = "default_ticks_per_minute")]
+    pub ticks_per_minute: i64,
+
+    /// The time of day at tick 0 in minutes after midnight.
+    #[serde(default = "default_start_time")]
+    pub start_time: i64,
 }
 
 impl Default for World {
@@ -13,8 +72,14 @@ impl Default for World {
 }
 
 impl World {
+    pub const MINUTES_PER_DAY: i64 = 24 * 60;
+
     pub fn new() -> Self {
-        Self { tick_counter: 0 }
+        Self {
+            tick_counter: 0,
+            ticks_per_minute: default_ticks_per_minute(),
+            start_time: default_start_time(),
+        }
     }
 
     /// Tick.
@@ -26,4 +91,151 @@ impl World {
     pub fn reset(&mut self) {
         self.tick_counter = 0;
     }
+
+    /// Returns the in-game minutes passed since the first day started at midnight.
+    pub fn total_minutes(&self) -> i64 {
+        self.start_time + self.tick_counter / self.ticks_per_minute.max(1)
+    }
+
+    /// Returns the current in-game date and time.
+    pub fn date(&self) -> WorldDate {
+        let total = self.total_minutes();
+        let minute_of_day = total % Self::MINUTES_PER_DAY;
+
+        WorldDate {
+            day: total / Self::MINUTES_PER_DAY + 1,
+            hours: minute_of_day / 60,
+            minutes: minute_of_day % 60,
+        }
+    }
+
+    /// Returns the current phase of the day.
+    pub fn day_phase(&self) -> DayPhase {
+        match self.date().hours {
+            5..=6 => DayPhase::Dawn,
+            7..=18 => DayPhase::Day,
+            19..=20 => DayPhase::Dusk,
+            _ => DayPhase::Night,
+        }
+    }
+
+    /// Returns the brightness of the daylight, 1.0 during the day and 0.0 at night with
+    /// linear transitions during dawn and dusk.
+    pub fn daylight(&self) -> f32 {
+        let minute_of_day = (self.total_minutes() % Self::MINUTES_PER_DAY) as f32;
+
+        match self.day_phase() {
+            DayPhase::Dawn => (minute_of_day - 5.0 * 60.0) / 120.0,
+            DayPhase::Day => 1.0,
+            DayPhase::Dusk => 1.0 - (minute_of_day - 19.0 * 60.0) / 120.0,
+            DayPhase::Night => 0.0,
+        }
+    }
+
+    /// Returns the number of ticks until the next occurrence of the given time of day. Returns
+    /// a full day if the time is now.
+    pub fn ticks_until(&self, hours: i64, minutes: i64) -> i64 {
+        let ticks_per_minute = self.ticks_per_minute.max(1);
+        let target = (hours * 60 + minutes).rem_euclid(Self::MINUTES_PER_DAY);
+
+        // Ticks since midnight of the current day.
+        let now = (self.start_time * ticks_per_minute + self.tick_counter)
+            .rem_euclid(Self::MINUTES_PER_DAY * ticks_per_minute);
+
+        let ticks = target * ticks_per_minute - now;
+        if ticks <= 0 {
+            ticks + self.ticks_per_day()
+        } else {
+            ticks
+        }
+    }
+
+    /// Returns the number of ticks of an in-game day.
+    pub fn ticks_per_day(&self) -> i64 {
+        Self::MINUTES_PER_DAY * self.ticks_per_minute.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: i64, hours: i64, minutes: i64) -> WorldDate {
+        WorldDate {
+            day,
+            hours,
+            minutes,
+        }
+    }
+
+    /// Returns a world at the given number of in-game minutes after the start time.
+    fn world_after(minutes: i64) -> World {
+        let mut world = World::new();
+        world.tick_counter = minutes * world.ticks_per_minute;
+        world
+    }
+
+    #[test]
+    fn date_starts_at_the_start_time() {
+        let now = World::new().date();
+        assert_eq!(now, date(1, 8, 0));
+        assert_eq!(now.to_string(), "Day 1 08:00");
+    }
+
+    #[test]
+    fn date_advances_with_the_ticks() {
+        let mut world = World::new();
+        for _ in 0..world.ticks_per_minute - 1 {
+            world.tick();
+        }
+        assert_eq!(world.date().minutes, 0);
+        world.tick();
+        assert_eq!(world.date().minutes, 1);
+
+        assert_eq!(world_after(16 * 60 + 30).date(), date(2, 0, 30));
+    }
+
+    #[test]
+    fn day_phase_and_daylight() {
+        let world = World::new();
+        assert_eq!(world.day_phase(), DayPhase::Day);
+        assert_eq!(world.daylight(), 1.0);
+
+        // 20:00
+        let world = world_after(12 * 60);
+        assert_eq!(world.day_phase(), DayPhase::Dusk);
+        assert_eq!(world.daylight(), 0.5);
+
+        // 22:00
+        let world = world_after(14 * 60);
+        assert_eq!(world.day_phase(), DayPhase::Night);
+        assert_eq!(world.daylight(), 0.0);
+
+        // 05:30 on the next day
+        let world = world_after(21 * 60 + 30);
+        assert_eq!(world.day_phase(), DayPhase::Dawn);
+        assert_eq!(world.daylight(), 0.25);
+        assert_eq!(world.day_phase().name(), "dawn");
+    }
+
+    #[test]
+    fn ticks_until_the_next_time_of_day() {
+        let mut world = World::new();
+        assert_eq!(world.ticks_until(9, 0), 60 * world.ticks_per_minute);
+        assert_eq!(world.ticks_until(7, 0), 23 * 60 * world.ticks_per_minute);
+        assert_eq!(world.ticks_until(8, 0), world.ticks_per_day());
+
+        world.tick();
+        assert_eq!(world.ticks_until(8, 1), world.ticks_per_minute - 1);
+        assert_eq!(world.ticks_until(8, 0), world.ticks_per_day() - 1);
+    }
+
+    #[test]
+    fn zero_ticks_per_minute_counts_as_one() {
+        let mut world = World::new();
+        world.ticks_per_minute = 0;
+        world.tick_counter = 60;
+        assert_eq!(world.date(), date(1, 9, 0));
+        assert_eq!(world.ticks_until(10, 0), 60);
+    }
 }
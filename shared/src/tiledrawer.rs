@@ -83,6 +83,58 @@ impl TileDrawer {
         //println!("drawing time {:?}", _stop - start);
     }
 
+    /// Darkens the pixels of the region by the brightness of the light map, interpolated
+    /// between the tiles.
+    pub fn draw_lighting(
+        &self,
+        buffer: &mut TheRGBABuffer,
+        region: &Region,
+        light_map: &LightMap,
+    ) {
+        let width = buffer.dim().width as usize;
+        let tile_size = region.grid_size as f32;
+
+        buffer
+            .pixels_mut()
+            .par_chunks_exact_mut(width * 4)
+            .enumerate()
+            .for_each(|(y, line)| {
+                let tile_y = (y as f32 + 0.5) / tile_size;
+                for (x, pixel) in line.chunks_exact_mut(4).enumerate() {
+                    let tile_x = (x as f32 + 0.5) / tile_size;
+                    let brightness = light_map.brightness_at(vec2f(tile_x, tile_y));
+                    for c in &mut pixel[0..3] {
+                        *c = (*c as f32 * brightness) as u8;
+                    }
+                }
+            });
+    }
+
+    /// Blacks out the tiles of the region which are not in the given set of visible tiles.
+    pub fn draw_fog(
+        &self,
+        buffer: &mut TheRGBABuffer,
+        region: &Region,
+        visible: &FxHashSet<(i32, i32)>,
+    ) {
+        let width = buffer.dim().width as usize;
+        let tile_size = region.grid_size;
+
+        buffer
+            .pixels_mut()
+            .par_chunks_exact_mut(width * 4)
+            .enumerate()
+            .for_each(|(y, line)| {
+                let tile_y = y as i32 / tile_size;
+                for (x, pixel) in line.chunks_exact_mut(4).enumerate() {
+                    let tile_x = x as i32 / tile_size;
+                    if !visible.contains(&(tile_x, tile_y)) {
+                        pixel.copy_from_slice(&BLACK);
+                    }
+                }
+            });
+    }
+
     pub fn draw_tile(
         &self,
         at: Vec2i,
@@ -12,6 +12,9 @@ pub struct Tilemap {
     pub zoom: f32,
 
     pub tiles: Vec<Tile>,
+
+    #[serde(default)]
+    pub autotiles: Vec<Autotile>,
 }
 
 impl Default for Tilemap {
@@ -33,6 +36,7 @@ impl Tilemap {
             zoom: 1.0,
 
             tiles: vec![],
+            autotiles: vec![],
         }
     }
 
@@ -40,6 +44,13 @@ impl Tilemap {
     pub fn set_buffer(&mut self, buffer: TheRGBABuffer) {
         self.buffer = buffer;
     }
+
+    /// Returns the autotile rule set with the given name.
+    pub fn get_autotile_mut(&mut self, name: &str) -> Option<&mut Autotile> {
+        self.autotiles
+            .iter_mut()
+            .find(|autotile| autotile.name == name)
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
@@ -126,6 +137,9 @@ pub struct Tile {
 
     pub sequence: TheRGBARegionSequence,
     pub blocking: bool,
+
+    #[serde(default)]
+    pub flags: TileFlags,
 }
 
 impl Default for Tile {
@@ -143,6 +157,233 @@ impl Tile {
 
             sequence: TheRGBARegionSequence::new(),
             blocking: false,
+
+            flags: TileFlags::default(),
+        }
+    }
+}
+
+/// The gameplay properties of a tile beyond blocking.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct TileFlags {
+    /// The tile blocks the field of view.
+    pub blocks_vision: bool,
+
+    /// Only characters which can swim (or use a boat) can enter the tile.
+    pub water: bool,
+
+    /// The hit points lost per tick by characters standing on the tile.
+    pub damage: i32,
+
+    /// The number of ticks needed to cross the tile.
+    pub movement_cost: i32,
+
+    /// The radius of the light emitted by the tile in tiles.
+    pub light: i32,
+}
+
+impl Default for TileFlags {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TileFlags {
+    pub fn new() -> Self {
+        Self {
+            blocks_vision: false,
+            water: false,
+            damage: 0,
+            movement_cost: 1,
+            light: 0,
+        }
+    }
+
+    /// Combines the flags of two tile layers on the same position.
+    pub fn combine(&self, other: &TileFlags) -> TileFlags {
+        TileFlags {
+            blocks_vision: self.blocks_vision || other.blocks_vision,
+            water: self.water || other.water,
+            damage: self.damage.max(other.damage),
+            movement_cost: self.movement_cost.max(other.movement_cost),
+            light: self.light.max(other.light),
+        }
+    }
+}
+
+/// The neighbors considered by an autotile rule set.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum AutotileBits {
+    /// The four sides: north 1, east 2, south 4, west 8.
+    Four,
+    /// The sides and corners: north 1, north east 2, east 4, south east 8, south 16,
+    /// south west 32, west 64, north west 128. A corner only counts if both adjacent sides match.
+    Eight,
+}
+
+/// A rule set which picks the tile variant, like a corner or an edge, from the matching
+/// neighbors of a tile. Neighbors match if they use any tile of the same rule set.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Autotile {
+    pub id: Uuid,
+    pub name: String,
+
+    pub bits: AutotileBits,
+
+    /// The tile for each neighbor mask.
+    pub tiles: FxHashMap<u8, Uuid>,
+}
+
+impl Autotile {
+    pub fn new(name: String, bits: AutotileBits) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+
+            bits,
+
+            tiles: FxHashMap::default(),
+        }
+    }
+
+    /// Returns true if the tile is part of the rule set.
+    pub fn contains(&self, tile: &Uuid) -> bool {
+        self.tiles.values().any(|id| id == tile)
+    }
+
+    /// Returns the neighbor mask, matches returns true if the neighbor at the given offset
+    /// belongs to the rule set.
+    pub fn mask<F: Fn(i32, i32) -> bool>(&self, matches: F) -> u8 {
+        let n = matches(0, -1);
+        let e = matches(1, 0);
+        let s = matches(0, 1);
+        let w = matches(-1, 0);
+
+        match self.bits {
+            AutotileBits::Four => n as u8 | (e as u8) << 1 | (s as u8) << 2 | (w as u8) << 3,
+            AutotileBits::Eight => {
+                let ne = n && e && matches(1, -1);
+                let se = s && e && matches(1, 1);
+                let sw = s && w && matches(-1, 1);
+                let nw = n && w && matches(-1, -1);
+
+                n as u8
+                    | (ne as u8) << 1
+                    | (e as u8) << 2
+                    | (se as u8) << 3
+                    | (s as u8) << 4
+                    | (sw as u8) << 5
+                    | (w as u8) << 6
+                    | (nw as u8) << 7
+            }
+        }
+    }
+
+    /// Returns the tile for the neighbor mask. For 8-bit rule sets without a tile for the mask
+    /// the corners are ignored.
+    pub fn tile_for_mask(&self, mask: u8) -> Option<Uuid> {
+        if let Some(tile) = self.tiles.get(&mask) {
+            return Some(*tile);
+        }
+        if self.bits == AutotileBits::Eight {
+            return self.tiles.get(&(mask & 0b0101_0101)).copied();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a closure matching the neighbors at the given offsets.
+    fn neighbors(offsets: &[(i32, i32)]) -> impl Fn(i32, i32) -> bool + '_ {
+        move |dx, dy| offsets.contains(&(dx, dy))
+    }
+
+    #[test]
+    fn four_bit_mask() {
+        let autotile = Autotile::new("Road".to_string(), AutotileBits::Four);
+
+        assert_eq!(autotile.mask(neighbors(&[])), 0);
+        assert_eq!(autotile.mask(neighbors(&[(0, -1), (1, 0)])), 0b0011);
+        assert_eq!(autotile.mask(neighbors(&[(0, 1), (-1, 0)])), 0b1100);
+        assert_eq!(autotile.mask(neighbors(&[(1, 1), (-1, -1)])), 0);
+    }
+
+    #[test]
+    fn eight_bit_mask_counts_corners_next_to_sides() {
+        let autotile = Autotile::new("Wall".to_string(), AutotileBits::Eight);
+        let all = [
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+        ];
+
+        assert_eq!(autotile.mask(neighbors(&all)), 0xff);
+        assert_eq!(autotile.mask(neighbors(&[(1, -1)])), 0);
+        assert_eq!(autotile.mask(neighbors(&[(0, -1), (1, 0)])), 0b0101);
+        assert_eq!(
+            autotile.mask(neighbors(&[(0, -1), (1, -1), (1, 0)])),
+            0b0111
+        );
+        assert_eq!(autotile.mask(neighbors(&[(0, 1), (-1, 1)])), 0b1_0000);
+    }
+
+    #[test]
+    fn tile_for_mask_with_fallback() {
+        let (full, corner) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut autotile = Autotile::new("Wall".to_string(), AutotileBits::Eight);
+        autotile.tiles.insert(0xff, full);
+        autotile.tiles.insert(0b0101, corner);
+
+        assert!(autotile.contains(&full));
+        assert!(!autotile.contains(&Uuid::new_v4()));
+        assert_eq!(autotile.tile_for_mask(0xff), Some(full));
+        assert_eq!(autotile.tile_for_mask(0b0111), Some(corner));
+        assert_eq!(autotile.tile_for_mask(0b0001), None);
+
+        autotile.bits = AutotileBits::Four;
+        assert_eq!(autotile.tile_for_mask(0b0101), Some(corner));
+        assert_eq!(autotile.tile_for_mask(0b0111), None);
+    }
+
+    #[test]
+    fn region_picks_the_variants_of_its_tiles() {
+        let (single, east, west, both) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let mut autotile = Autotile::new("Road".to_string(), AutotileBits::Four);
+        autotile.tiles.insert(0, single);
+        autotile.tiles.insert(0b0010, east);
+        autotile.tiles.insert(0b1000, west);
+        autotile.tiles.insert(0b1010, both);
+
+        let mut region = Region::new();
+        let other = Uuid::new_v4();
+        for x in 0..3 {
+            region.set_tile((x, 0), Layer2DRole::Ground, Some(single));
         }
+        region.set_tile((3, 0), Layer2DRole::Ground, Some(other));
+
+        let positions = region.autotile_neighborhood(&[(1, 0)]);
+        assert_eq!(positions.len(), 6);
+        region.update_autotiles(&positions, &[autotile]);
+
+        let layer = |x| region.layer_at(vec2i(x, 0), Layer2DRole::Ground);
+        assert_eq!(layer(0), Some(east));
+        assert_eq!(layer(1), Some(both));
+        assert_eq!(layer(2), Some(west));
+        assert_eq!(layer(3), Some(other));
     }
 }
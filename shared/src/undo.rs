@@ -0,0 +1,300 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+/// A compact undo step of a region edit. Records only the changed tiles, the added, removed or
+/// moved character and item instances and areas as (before, after) pairs, None meaning absent,
+/// and the size of the region if it changed.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct RegionUndo {
+    pub region_id: Uuid,
+
+    #[serde(with = "vectorize")]
+    pub tiles: FxHashMap<(i32, i32), (Option<RegionTile>, Option<RegionTile>)>,
+    pub characters: Vec<(Uuid, Option<Character>, Option<Character>)>,
+    pub items: Vec<(Uuid, Option<Item>, Option<Item>)>,
+
+    #[serde(default)]
+    pub areas: Vec<(Uuid, Option<Area>, Option<Area>)>,
+    #[serde(default)]
+    pub size: Option<((i32, i32), (i32, i32))>,
+}
+
+impl RegionUndo {
+    pub fn new(region_id: Uuid) -> Self {
+        Self {
+            region_id,
+            tiles: FxHashMap::default(),
+            characters: vec![],
+            items: vec![],
+            areas: vec![],
+            size: None,
+        }
+    }
+
+    /// Returns true if the undo step does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+            && self.characters.is_empty()
+            && self.items.is_empty()
+            && self.areas.is_empty()
+            && self.size.is_none()
+    }
+
+    /// Records the change of the tile at the given position. Repeated changes of the same
+    /// tile keep the original state, so that a whole brush stroke is undone at once.
+    pub fn record_tile(
+        &mut self,
+        pos: (i32, i32),
+        before: Option<RegionTile>,
+        after: Option<RegionTile>,
+    ) {
+        if let Some((original, _)) = self.tiles.get(&pos) {
+            if *original == after {
+                self.tiles.remove(&pos);
+            } else {
+                self.tiles.insert(pos, (original.clone(), after));
+            }
+        } else if before != after {
+            self.tiles.insert(pos, (before, after));
+        }
+    }
+
+    /// Records the addition (before is None), removal (after is None) or change of a character
+    /// instance.
+    pub fn record_character(
+        &mut self,
+        id: Uuid,
+        before: Option<Character>,
+        after: Option<Character>,
+    ) {
+        record(&mut self.characters, id, before, after);
+    }
+
+    /// Records the addition (before is None), removal (after is None) or change of an item
+    /// instance.
+    pub fn record_item(&mut self, id: Uuid, before: Option<Item>, after: Option<Item>) {
+        record(&mut self.items, id, before, after);
+    }
+
+    /// Records the addition (before is None), removal (after is None) or change of an area.
+    pub fn record_area(&mut self, id: Uuid, before: Option<Area>, after: Option<Area>) {
+        record(&mut self.areas, id, before, after);
+    }
+
+    /// Records the change of the region size as (width, height).
+    pub fn record_size(&mut self, before: (i32, i32), after: (i32, i32)) {
+        let original = self.size.map_or(before, |(original, _)| original);
+        self.size = if original != after {
+            Some((original, after))
+        } else {
+            None
+        };
+    }
+
+    /// Sets the tiles, characters and items of the region to their state after the edit.
+    pub fn apply(&self, region: &mut Region) {
+        for (pos, (_, after)) in &self.tiles {
+            if let Some(tile) = after {
+                region.tiles.insert(*pos, tile.clone());
+            } else {
+                region.tiles.remove(pos);
+            }
+        }
+        for (id, _, after) in &self.characters {
+            if let Some(character) = after {
+                region.characters.insert(*id, character.clone());
+            } else {
+                region.characters.remove(id);
+            }
+        }
+        for (id, _, after) in &self.items {
+            if let Some(item) = after {
+                region.items.insert(*id, item.clone());
+            } else {
+                region.items.remove(id);
+            }
+        }
+        for (id, _, after) in &self.areas {
+            if let Some(area) = after {
+                region.areas.insert(*id, area.clone());
+            } else {
+                region.areas.remove(id);
+            }
+        }
+        if let Some((_, (width, height))) = self.size {
+            region.width = width;
+            region.height = height;
+        }
+    }
+
+    /// Returns the inverse of the edit, applying it reverts the edit.
+    pub fn inverted(&self) -> Self {
+        Self {
+            region_id: self.region_id,
+            tiles: self
+                .tiles
+                .iter()
+                .map(|(pos, (before, after))| (*pos, (after.clone(), before.clone())))
+                .collect(),
+            characters: invert(&self.characters),
+            items: invert(&self.items),
+            areas: invert(&self.areas),
+            size: self.size.map(|(before, after)| (after, before)),
+        }
+    }
+
+    /// Create an undo step from json.
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    /// Convert the undo step to json.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap_or_default()
+    }
+}
+
+/// Records a change, merging it with an earlier change of the same key. Changes which end up
+/// in their original state are dropped.
+#[allow(clippy::type_complexity)]
+fn record<K: PartialEq, V: PartialEq>(
+    changes: &mut Vec<(K, Option<V>, Option<V>)>,
+    key: K,
+    before: Option<V>,
+    after: Option<V>,
+) {
+    if let Some(index) = changes.iter().position(|(k, _, _)| *k == key) {
+        changes[index].2 = after;
+        if changes[index].1 == changes[index].2 {
+            changes.remove(index);
+        }
+    } else if before != after {
+        changes.push((key, before, after));
+    }
+}
+
+/// Swaps the before and after states of the changes, in reverse order.
+#[allow(clippy::type_complexity)]
+fn invert<K: Clone, V: Clone>(
+    changes: &[(K, Option<V>, Option<V>)],
+) -> Vec<(K, Option<V>, Option<V>)> {
+    changes
+        .iter()
+        .rev()
+        .map(|(key, before, after)| (key.clone(), after.clone(), before.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(id: Uuid) -> RegionTile {
+        let mut tile = RegionTile::new();
+        tile.layers[0] = Some(id);
+        tile
+    }
+
+    #[test]
+    fn record_tile_keeps_the_original_state() {
+        let (a, b, c) = (
+            tile(Uuid::new_v4()),
+            tile(Uuid::new_v4()),
+            tile(Uuid::new_v4()),
+        );
+        let mut region_undo = RegionUndo::new(Uuid::new_v4());
+
+        region_undo.record_tile((0, 0), Some(a.clone()), Some(b.clone()));
+        region_undo.record_tile((0, 0), Some(b), Some(c.clone()));
+        assert_eq!(
+            region_undo.tiles[&(0, 0)],
+            (Some(a.clone()), Some(c.clone()))
+        );
+
+        // Back to the original state, nothing changed.
+        region_undo.record_tile((0, 0), Some(c), Some(a.clone()));
+        assert!(region_undo.is_empty());
+
+        region_undo.record_tile((1, 0), Some(a.clone()), Some(a));
+        assert!(region_undo.is_empty());
+    }
+
+    #[test]
+    fn record_merges_changes_of_the_same_instance() {
+        let mut region_undo = RegionUndo::new(Uuid::new_v4());
+        let character = Character::new();
+        let mut moved = character.clone();
+        moved.character_id = Uuid::new_v4();
+
+        region_undo.record_character(character.id, None, Some(character.clone()));
+        region_undo.record_character(character.id, Some(character.clone()), Some(moved.clone()));
+        assert_eq!(
+            region_undo.characters,
+            vec![(character.id, None, Some(moved.clone()))]
+        );
+
+        // Added and removed again.
+        region_undo.record_character(character.id, Some(moved), None);
+        assert!(region_undo.is_empty());
+
+        let area = Area::new();
+        region_undo.record_area(area.id, Some(area.clone()), Some(area.clone()));
+        assert!(region_undo.is_empty());
+    }
+
+    #[test]
+    fn record_size_keeps_the_original_size() {
+        let mut region_undo = RegionUndo::new(Uuid::new_v4());
+        region_undo.record_size((10, 10), (12, 10));
+        region_undo.record_size((12, 10), (12, 8));
+        assert_eq!(region_undo.size, Some(((10, 10), (12, 8))));
+
+        region_undo.record_size((12, 8), (10, 10));
+        assert_eq!(region_undo.size, None);
+    }
+
+    #[test]
+    fn inverted_undo_reverts_apply() {
+        let mut region = Region::new();
+        region.set_tile((1, 1), Layer2DRole::Ground, Some(Uuid::new_v4()));
+        let area = Area::new();
+        region.areas.insert(area.id, area.clone());
+        let original = region.clone();
+
+        let mut region_undo = RegionUndo::new(region.id);
+        let before = region.tiles.get(&(1, 1)).cloned();
+        region_undo.record_tile((1, 1), before, None);
+        region_undo.record_tile((2, 2), None, Some(tile(Uuid::new_v4())));
+        let character = Character::new();
+        region_undo.record_character(character.id, None, Some(character.clone()));
+        let item = Item::new();
+        region_undo.record_item(item.id, None, Some(item.clone()));
+        region_undo.record_area(area.id, Some(area), None);
+        region_undo.record_size((80, 80), (40, 20));
+
+        region_undo.apply(&mut region);
+        assert!(!region.tiles.contains_key(&(1, 1)));
+        assert!(region.tiles.contains_key(&(2, 2)));
+        assert!(region.characters.contains_key(&character.id));
+        assert!(region.items.contains_key(&item.id));
+        assert!(region.areas.is_empty());
+        assert_eq!((region.width, region.height), (40, 20));
+
+        region_undo.inverted().apply(&mut region);
+        assert_eq!(region, original);
+        assert_eq!(region_undo.inverted().inverted(), region_undo);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut region_undo = RegionUndo::new(Uuid::new_v4());
+        region_undo.record_tile((3, 4), None, Some(tile(Uuid::new_v4())));
+        region_undo.record_size((1, 1), (2, 2));
+
+        assert_eq!(
+            RegionUndo::from_json(&region_undo.to_json()),
+            Some(region_undo)
+        );
+        assert_eq!(RegionUndo::from_json("{}"), None);
+    }
+}